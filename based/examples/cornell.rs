@@ -0,0 +1,51 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::environment::Environment;
+use based::material::Material::{Lambertian, DiffuseLight, Dielectric};
+use based::hittable::{HittableList, Sphere};
+use based::hittable::instance::Instance;
+use based::hittable::shapes::{Cuboid, Quad};
+use glitz::transform::Transform;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use std::sync::Arc;
+
+fn main() {
+    let aspect_ratio = 1.0;
+    let image_width = 600;
+    let num_samples = 200;
+
+    // World
+    let red = Lambertian(Arc::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Lambertian(Arc::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Lambertian(Arc::new(Color::new(0.12, 0.45, 0.15)));
+    let light = DiffuseLight(Arc::new(Color::new(15.0, 15.0, 15.0)));
+
+    let mut world = HittableList::new();
+    world.add(Quad::new(Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0), green));
+    world.add(Quad::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 555.0), Vec3::new(0.0, 555.0, 0.0), red));
+    world.add(Quad::new(Vec3::new(343.0, 554.0, 332.0), Vec3::new(-130.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -105.0), light));
+    world.add(Quad::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 555.0), white.clone()));
+    world.add(Quad::new(Vec3::new(555.0, 555.0, 555.0), Vec3::new(-555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -555.0), white.clone()));
+    world.add(Quad::new(Vec3::new(0.0, 0.0, 555.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), white.clone()));
+    let tall_box = Cuboid::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(165.0, 330.0, 165.0), white);
+    let place = Transform::rotate(Vec3::new(0.0, 1.0, 0.0), 15f64.to_radians()).then(&Transform::translate(Vec3::new(265.0, 0.0, 295.0)));
+    world.add(Instance::new(Arc::new(tall_box), place));
+    world.add(Sphere::new(Vec3::new(190.0, 90.0, 190.0), 90.0, Dielectric(1.5)));
+
+    // Camera
+    let lookfrom = Vec3::new(278.0, 278.0, -800.0);
+    let lookat = Vec3::new(278.0, 278.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 40.0, aspect_ratio, aperture, dist_to_focus);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .environment(Environment::None)
+        .render_to_file("cornell.png")
+}
@@ -0,0 +1,39 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::environment::{Environment, EnvironmentMap};
+use based::material::Material::{Metal, Dielectric, Lambertian};
+use based::hittable::{HittableList, Sphere};
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use std::sync::Arc;
+
+fn main() {
+    let path = std::env::args().nth(1).expect("usage: envmap <equirectangular .hdr file>");
+    let map = EnvironmentMap::load(&path).unwrap_or_else(|e| panic!("can't load {}: {}", path, e));
+
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width = 800;
+    let num_samples = 100;
+
+    // World
+    let mut world = HittableList::new();
+    world.add(Sphere::new(Vec3::new(0.0, -1000.5, 0.0), 1000.0, Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))));
+    world.add(Sphere::new(Vec3::new(-1.1, 0.0, 0.0), 0.5, Lambertian(Arc::new(Color::new(0.8, 0.3, 0.3)))));
+    world.add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 0.5, Metal(Arc::new(Color::new(0.9, 0.9, 0.9)), 0.0)));
+    world.add(Sphere::new(Vec3::new(1.1, 0.0, 0.0), 0.5, Dielectric(1.5)));
+
+    // Camera
+    let lookfrom = Vec3::new(0.0, 1.0, 5.0);
+    let lookat = Vec3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = (lookfrom - lookat).length();
+    let aperture = 0.0;
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 30.0, aspect_ratio, aperture, dist_to_focus);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .environment(Environment::Map(Arc::new(map)))
+        .render_to_file("envmap.png")
+}
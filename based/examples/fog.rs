@@ -0,0 +1,42 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::material::Material::{Dielectric, DiffuseLight, Interface, Lambertian};
+use based::hittable::{HittableList, Sphere};
+use based::hittable::volume::Volume;
+use based::medium::Homogeneous;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use std::sync::Arc;
+
+// A cloud of smoke and a glass of milk in a foggy night, lit by a single lamp.
+fn main() {
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width = 400;
+    let num_samples = 200;
+
+    // World
+    let mut world = HittableList::new();
+    world.add(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))));
+    world.add(Sphere::new(Vec3::new(0.0, 4.0, -1.0), 0.5, DiffuseLight(Arc::new(Color::new(40.0, 35.0, 25.0)))));
+
+    let smoke = Homogeneous::new(Color::new(0.3, 0.3, 0.3), Color::new(1.5, 1.5, 1.5));
+    world.add(Volume::new(Sphere::new(Vec3::new(-1.6, 1.0, 0.0), 1.0, Interface), smoke));
+    let milk = Homogeneous::new(Color::new(0.0, 0.01, 0.05), Color::new(20.0, 20.0, 20.0)).anisotropy(0.7);
+    world.add(Volume::new(Sphere::new(Vec3::new(1.6, 1.0, 0.0), 1.0, Dielectric(1.35)), milk));
+
+    // Camera
+    let lookfrom = Vec3::new(0.0, 2.0, 9.0);
+    let lookat = Vec3::new(0.0, 1.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 9.0;
+    let aperture = 0.0;
+
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 35.0, aspect_ratio, aperture, dist_to_focus);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .medium(Homogeneous::new(Color::new(0.02, 0.02, 0.02), Color::new(0.03, 0.03, 0.03)))
+        .render_to_file("fog.png")
+}
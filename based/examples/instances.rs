@@ -0,0 +1,57 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::material::Material::{Lambertian, Metal};
+use based::hittable::{Hittable, HittableList, Sphere};
+use based::hittable::bvh::Bvh;
+use based::hittable::instance::Instance;
+use based::hittable::mesh::TriangleMesh;
+use glitz::transform::Transform;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256Plus;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// A field of crystals, all instances of the one mesh, so it and its BVH are stored once.
+fn main() {
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width = 800;
+    let num_samples = 100;
+
+    // An octahedron stretched upwards.
+    let positions = vec![
+        Vec3::new(0.0, 1.5, 0.0), Vec3::new(0.0, -0.5, 0.0),
+        Vec3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.5, 0.5), Vec3::new(-0.5, 0.5, 0.0), Vec3::new(0.0, 0.5, -0.5),
+    ];
+    let indices = vec![[0, 2, 3], [0, 3, 4], [0, 4, 5], [0, 5, 2], [1, 3, 2], [1, 4, 3], [1, 5, 4], [1, 2, 5]];
+    let crystal = TriangleMesh::new(positions, indices, Metal(Arc::new(Color::new(0.6, 0.4, 0.8)), 0.2));
+    let crystal: Arc<dyn Hittable> = Arc::new(Bvh::new(crystal.into_triangles()));
+
+    let mut rng = Xoshiro256Plus::seed_from_u64(3);
+    let mut world = HittableList::new();
+    world.add(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))));
+    for i in -50..50 {
+        for j in -50..50 {
+            let transform = Transform::scale(Vec3::new(1.0, rng.gen_range(0.5..1.5), 1.0) * rng.gen_range(0.1..0.3))
+                .then(&Transform::rotate(Vec3::new(rng.gen_range(-0.2..0.2), 1.0, rng.gen_range(-0.2..0.2)), rng.gen_range(0.0..2.0 * PI)))
+                .then(&Transform::translate(Vec3::new(i as f64 + rng.gen_range(0.0..0.8), 0.0, j as f64 + rng.gen_range(0.0..0.8))));
+            world.add(Instance::new(crystal.clone(), transform));
+        }
+    }
+
+    // Camera
+    let lookfrom = Vec3::new(0.0, 3.0, 12.0);
+    let lookat = Vec3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 12.0;
+    let aperture = 0.1;
+
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 30.0, aspect_ratio, aperture, dist_to_focus);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .render_to_file("instances.png")
+}
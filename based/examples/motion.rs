@@ -0,0 +1,56 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::material::Material::{Lambertian, Metal};
+use based::hittable::{Hittable, HittableList, Sphere};
+use based::hittable::bvh::Bvh;
+use based::hittable::instance::AnimatedInstance;
+use based::hittable::mesh::TriangleMesh;
+use glitz::transform::{AnimatedTransform, Keyframe};
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Things moving while the shutter is open: a falling ball, a rolling one and a spinning cube.
+fn main() {
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width = 800;
+    let num_samples = 200;
+
+    // World
+    let mut world = HittableList::new();
+    world.add(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))));
+    world.add(Sphere::moving(Vec3::new(-2.5, 1.6, 0.0), Vec3::new(-2.5, 0.8, 0.0), 0.8, Lambertian(Arc::new(Color::new(0.8, 0.2, 0.1)))));
+    world.add(Sphere::moving(Vec3::new(-0.6, 0.8, 1.5), Vec3::new(0.6, 0.8, 1.5), 0.8, Metal(Arc::new(Color::new(0.8, 0.8, 0.8)), 0.05)));
+
+    let positions = (0..8).map(|i| Vec3::new((i & 1) as f64, ((i >> 1) & 1) as f64, (i >> 2) as f64) - Vec3::new(0.5, 0.5, 0.5)).collect();
+    let indices = vec![
+        [0, 2, 1], [1, 2, 3], [4, 5, 6], [5, 7, 6], [0, 1, 4], [1, 5, 4],
+        [2, 6, 3], [3, 6, 7], [0, 4, 2], [2, 4, 6], [1, 3, 5], [3, 7, 5],
+    ];
+    let cube: Arc<dyn Hittable> = Arc::new(Bvh::new(TriangleMesh::new(positions, indices, Lambertian(Arc::new(Color::new(0.1, 0.3, 0.7)))).into_triangles()));
+    let axis = Vec3::new(0.0, 1.0, 0.0);
+    let spin = AnimatedTransform::new((0..=4).map(|i| {
+        Keyframe::new(i as f64 / 4.0)
+            .rotation(axis, i as f64 * PI / 8.0)
+            .scale(Vec3::new(1.4, 1.4, 1.4))
+            .translation(Vec3::new(2.5, 0.7, 0.0))
+    }).collect());
+    world.add(AnimatedInstance::new(cube, spin));
+
+    // Camera
+    let lookfrom = Vec3::new(0.0, 3.0, 10.0);
+    let lookat = Vec3::new(0.0, 0.8, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 35.0, aspect_ratio, aperture, dist_to_focus)
+        .shutter(0.0, 1.0);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .render_to_file("motion.png")
+}
@@ -1,17 +1,18 @@
 use xenon::color::Color;
 use based::material::Material::{Lambertian, Metal, Dielectric};
-use based::hittable::Sphere;
+use based::hittable::{HittableList, Sphere};
 use based::renderer::Renderer;
 use based::random::with_rng;
 use based::camera::SimpleCamera;
 use glitz::vec::Vec3;
 use rand::Rng;
+use std::sync::Arc;
 
-fn random_scene() -> Vec<Sphere> {
-    let ground_mat = Lambertian(Color::new(0.5, 0.5, 0.5));
-    let mut world = Vec::new();
+fn random_scene() -> HittableList {
+    let ground_mat = Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)));
+    let mut world = HittableList::new();
 
-    world.push(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat));
+    world.add(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, ground_mat));
 
     for a in -11..11 {
         for b in -11..11 {
@@ -21,29 +22,29 @@ fn random_scene() -> Vec<Sphere> {
             if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 {
                     let albedo = Color::new(with_rng(Rng::gen::<f64>), with_rng(Rng::gen::<f64>), with_rng(Rng::gen::<f64>)) * Color::new(with_rng(Rng::gen::<f64>), with_rng(Rng::gen::<f64>), with_rng(Rng::gen::<f64>));
-                    let sphere_mat = Lambertian(albedo);
-                    world.push(Sphere::new(center, 0.2, sphere_mat));
+                    let sphere_mat = Lambertian(Arc::new(albedo));
+                    world.add(Sphere::new(center, 0.2, sphere_mat));
                 } else if choose_mat < 0.95 {
                     let albedo = Color::new(with_rng(|r| r.gen_range(0.5..1.0)), with_rng(|r| r.gen_range(0.5..1.0)), with_rng(|r| r.gen_range(0.5..1.0)));
                     let fuzz = with_rng(Rng::gen::<f64>);
-                    let sphere_mat = Metal(albedo, fuzz);
-                    world.push(Sphere::new(center, 0.2, sphere_mat));
+                    let sphere_mat = Metal(Arc::new(albedo), fuzz);
+                    world.add(Sphere::new(center, 0.2, sphere_mat));
                 } else {
                     let sphere_mat = Dielectric(1.5);
-                    world.push(Sphere::new(center, 0.2, sphere_mat));
+                    world.add(Sphere::new(center, 0.2, sphere_mat));
                 }
             }
         }
     }
 
     let mat1 = Dielectric(1.5);
-    world.push(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1));
+    world.add(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1));
 
-    let mat2 = Lambertian(Color::new(0.4, 0.2, 0.1));
-    world.push(Sphere::new(Vec3::new(-4.0, 1.0, 0.0), 1.0, mat2));
+    let mat2 = Lambertian(Arc::new(Color::new(0.4, 0.2, 0.1)));
+    world.add(Sphere::new(Vec3::new(-4.0, 1.0, 0.0), 1.0, mat2));
 
-    let mat3 = Metal(Color::new(0.7, 0.6, 0.5), 0.0);
-    world.push(Sphere::new(Vec3::new(4.0, 1.0, 0.0), 1.0, mat3));
+    let mat3 = Metal(Arc::new(Color::new(0.7, 0.6, 0.5)), 0.0);
+    world.add(Sphere::new(Vec3::new(4.0, 1.0, 0.0), 1.0, mat3));
 
     world
 }
@@ -0,0 +1,48 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::material::Material::Lambertian;
+use based::hittable::{HittableList, Sphere};
+use based::texture::{Checker, Marble, NoiseTexture, Texture, Turbulence, Wood};
+use glitz::noise::{Noise, Perlin, Simplex, Worley};
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use std::sync::Arc;
+
+fn main() {
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width = 800;
+    let num_samples = 100;
+
+    let color = |r, g, b| -> Arc<dyn Texture> { Arc::new(Color::new(r, g, b)) };
+    let perlin: Arc<dyn Noise> = Arc::new(Perlin::new(1));
+    let simplex: Arc<dyn Noise> = Arc::new(Simplex::new(2));
+    let worley: Arc<dyn Noise> = Arc::new(Worley::new(3));
+
+    let ground = Checker::new(color(0.2, 0.3, 0.1), color(0.9, 0.9, 0.9), 2.0);
+    let marble = Marble::new(perlin.clone(), 2.0, 6.0, color(0.9, 0.9, 0.85), color(0.15, 0.15, 0.2));
+    let wood = Wood::new(perlin, 6.0, 0.4, color(0.75, 0.5, 0.3), color(0.35, 0.2, 0.1));
+    let clouds = Turbulence::new(simplex, 3.0, color(0.2, 0.4, 0.8), color(0.95, 0.95, 0.95));
+    let cells = NoiseTexture::new(worley, 8.0, color(0.1, 0.1, 0.1), color(0.9, 0.6, 0.1));
+
+    // World
+    let mut world = HittableList::new();
+    world.add(Sphere::new(Vec3::new(0.0, -1000.5, 0.0), 1000.0, Lambertian(Arc::new(ground))));
+    world.add(Sphere::new(Vec3::new(-1.65, 0.0, 0.0), 0.5, Lambertian(Arc::new(marble))));
+    world.add(Sphere::new(Vec3::new(-0.55, 0.0, 0.0), 0.5, Lambertian(Arc::new(wood))));
+    world.add(Sphere::new(Vec3::new(0.55, 0.0, 0.0), 0.5, Lambertian(Arc::new(clouds))));
+    world.add(Sphere::new(Vec3::new(1.65, 0.0, 0.0), 0.5, Lambertian(Arc::new(cells))));
+
+    // Camera
+    let lookfrom = Vec3::new(0.0, 1.5, 6.0);
+    let lookat = Vec3::new(0.0, 0.0, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = (lookfrom - lookat).length();
+    let aperture = 0.0;
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 30.0, aspect_ratio, aperture, dist_to_focus);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .render_to_file("procedural.png")
+}
@@ -0,0 +1,47 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::material::Material::{Lambertian, Metal, Dielectric, DiffuseLight};
+use based::hittable::HittableList;
+use based::hittable::shapes::{Cone, Cuboid, Cylinder, Disk, Plane, Quad, Torus};
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use std::sync::Arc;
+
+fn main() {
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width = 800;
+    let num_samples = 200;
+
+    // World
+    let ground = Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)));
+    let red = Lambertian(Arc::new(Color::new(0.7, 0.1, 0.1)));
+    let blue = Lambertian(Arc::new(Color::new(0.1, 0.2, 0.6)));
+    let gold = Metal(Arc::new(Color::new(0.9, 0.7, 0.3)), 0.1);
+    let glass = Dielectric(1.5);
+    let light = DiffuseLight(Arc::new(Color::new(6.0, 6.0, 6.0)));
+
+    let mut world = HittableList::new();
+    world.add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), ground));
+    world.add(Cuboid::new(Vec3::new(-3.5, 0.0, -0.5), Vec3::new(-2.5, 1.0, 0.5), red));
+    world.add(Cylinder::new(Vec3::new(-1.2, 0.0, 0.0), 0.5, 1.2, blue.clone()));
+    world.add(Disk::new(Vec3::new(-1.2, 1.2, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.5, blue));
+    world.add(Cone::new(Vec3::new(0.3, 0.0, 0.0), 0.6, 1.5, gold.clone()));
+    world.add(Torus::new(Vec3::new(1.9, 0.3, 0.0), 0.6, 0.3, gold));
+    world.add(Cuboid::new(Vec3::new(3.0, 0.0, -0.4), Vec3::new(3.8, 1.6, 0.4), glass));
+    world.add(Quad::new(Vec3::new(-2.0, 4.0, -1.0), Vec3::new(0.0, 0.0, 2.0), Vec3::new(4.0, 0.0, 0.0), light));
+
+    // Camera
+    let lookfrom = Vec3::new(0.0, 3.0, 9.0);
+    let lookat = Vec3::new(0.0, 0.6, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 9.0;
+    let aperture = 0.0;
+
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 35.0, aspect_ratio, aperture, dist_to_focus);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .render_to_file("shapes.png")
+}
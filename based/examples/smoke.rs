@@ -0,0 +1,62 @@
+use based::renderer::Renderer;
+use based::camera::SimpleCamera;
+use based::material::Material::{DiffuseLight, Interface, Lambertian};
+use based::hittable::{HittableList, Sphere};
+use based::hittable::volume::Volume;
+use based::import::raw::load_raw_grid;
+use based::medium::{DensityGrid, GridMedium};
+use glitz::aabb::Aabb;
+use glitz::noise::{fbm, Simplex};
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use std::env;
+use std::sync::Arc;
+
+// A puff of smoke over a floor, lit from above. Pass a raw voxel file and its resolution,
+// as in `smoke density.raw 64 64 64`, to render that instead of the puff made from noise.
+fn main() {
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width = 400;
+    let num_samples = 100;
+
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let grid = match args.as_slice() {
+        [path, x, y, z] => {
+            let resolution = [x.parse().unwrap(), y.parse().unwrap(), z.parse().unwrap()];
+            load_raw_grid(path, resolution).unwrap()
+        }
+        _ => {
+            let noise = Simplex::new(7);
+            DensityGrid::from_fn([64, 64, 64], |p| {
+                let falloff = 1.0 - 2.8 * (p - Vec3::new(0.5, 0.5, 0.5)).length();
+                (falloff + 0.6 * fbm(&noise, &(4.0 * p), 5, 2.0, 0.5)).max(0.0) * 2.0
+            })
+        }
+    };
+
+    // World
+    let mut world = HittableList::new();
+    world.add(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))));
+    world.add(Sphere::new(Vec3::new(3.0, 6.0, 2.0), 1.0, DiffuseLight(Arc::new(Color::new(20.0, 20.0, 20.0)))));
+
+    // The grid fills a box, which a sphere just around it marks out as the volume.
+    let bounds = Aabb::new(Vec3::new(-1.5, 0.0, -1.5), Vec3::new(1.5, 3.0, 1.5));
+    let smoke = GridMedium::new(grid, bounds, 4.0, Color::new(0.9, 0.9, 0.9)).anisotropy(0.3);
+    let boundary = Sphere::new(bounds.centroid(), bounds.diagonal().length() / 2.0 + 0.01, Interface);
+    world.add(Volume::new(boundary, smoke));
+
+    // Camera
+    let lookfrom = Vec3::new(0.0, 2.5, 10.0);
+    let lookat = Vec3::new(0.0, 1.5, 0.0);
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+    let dist_to_focus = 10.0;
+    let aperture = 0.0;
+
+    let cam = SimpleCamera::new(lookfrom, lookat, vup, 30.0, aspect_ratio, aperture, dist_to_focus);
+
+    Renderer::new(world, cam)
+        .width(image_width)
+        .aspect_ratio(aspect_ratio)
+        .num_samples(num_samples)
+        .render_to_file("smoke.png")
+}
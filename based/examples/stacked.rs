@@ -1,9 +1,12 @@
 use based::renderer::Renderer;
 use based::camera::SimpleCamera;
-use based::material::Material::{Metal, Dielectric, Lambertian};
-use based::hittable::Sphere;
+use based::material::Conductor;
+use based::material::Material::{self, Metal, Dielectric, Lambertian};
+use based::hittable::{HittableList, Sphere};
+use based::hittable::shapes::Plane;
 use glitz::vec::Vec3;
 use xenon::color::Color;
+use std::sync::Arc;
 
 fn main() {
     let aspect_ratio = 16.0 / 9.0;
@@ -11,21 +14,21 @@ fn main() {
     let num_samples = 500;
 
     // World
-    let ground = Lambertian(Color::new(0.4, 0.6, 0.6));
-    let blue = Lambertian(Color::new(0.1, 0.2, 0.5));
-    let red = Lambertian(Color::new(0.9, 0.05, 0.05));
-    let gold = Metal(Color::new(0.8, 0.6, 0.2), 0.0);
-    let malachite = Metal(Color::new(0.2, 0.8, 0.2), 0.3);
+    let ground = Lambertian(Arc::new(Color::new(0.4, 0.6, 0.6)));
+    let blue = Lambertian(Arc::new(Color::new(0.1, 0.2, 0.5)));
+    let red = Lambertian(Arc::new(Color::new(0.9, 0.05, 0.05)));
+    let gold = Material::Conductor(Conductor::gold(0.15));
+    let malachite = Metal(Arc::new(Color::new(0.2, 0.8, 0.2)), 0.3);
     let glass = Dielectric(2.8);
     let glass2 = Dielectric(1.5);
-    let mut world = Vec::default();
-    world.push(Sphere::new(Vec3::new(0.0, -1000.75, 0.0), 1000.0, ground));
-    world.push(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 0.75, blue));
-    world.push(Sphere::new(Vec3::new(0.0, 0.0, 1.6), 0.75, gold));
-    world.push(Sphere::new(Vec3::new(0.0, 0.0, -1.6), 0.75, glass));
-    world.push(Sphere::new(Vec3::new(0.0, 1.2, 0.8), 0.75, red));
-    world.push(Sphere::new(Vec3::new(0.0, 1.2, -0.8), 0.75, malachite));
-    world.push(Sphere::new(Vec3::new(0.0, 2.4, 0.0), 0.75, glass2));
+    let mut world = HittableList::new();
+    world.add(Plane::new(Vec3::new(0.0, -0.75, 0.0), Vec3::new(0.0, 1.0, 0.0), ground));
+    world.add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 0.75, blue));
+    world.add(Sphere::new(Vec3::new(0.0, 0.0, 1.6), 0.75, gold));
+    world.add(Sphere::new(Vec3::new(0.0, 0.0, -1.6), 0.75, glass));
+    world.add(Sphere::new(Vec3::new(0.0, 1.2, 0.8), 0.75, red));
+    world.add(Sphere::new(Vec3::new(0.0, 1.2, -0.8), 0.75, malachite));
+    world.add(Sphere::new(Vec3::new(0.0, 2.4, 0.0), 0.75, glass2));
 
     // Camera
     let lookfrom = Vec3::new(17.0, 4.0, 3.0);
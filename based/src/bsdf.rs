@@ -0,0 +1,277 @@
+use std::f64::consts::PI;
+use glitz::frame::Frame;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::hittable::Hit;
+use crate::microfacet::{eval_dielectric, pdf_dielectric, sample_dielectric, TrowbridgeReitz};
+use crate::ray::{Ray, RayDifferential};
+use crate::sampling::{cosine_hemisphere, uniform_sphere};
+
+/// A direction picked by `Bxdf::sample` or `Bsdf::sample`.
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample {
+    pub wi: Vec3,
+    /// What light arriving along `wi` is multiplied by on its way to `wo`: the scattering
+    /// function, cosine included, over `pdf`.
+    pub weight: Color,
+    /// Solid angle density with which `wi` was picked, or for delta samples the chance of
+    /// having picked it out of a handful of directions.
+    pub pdf: f64,
+    /// Whether `wi` is one of a handful of directions that can't be evaluated or picked by
+    /// anything else.
+    pub is_delta: bool,
+    /// Index of refraction on the far side of the surface over that on the near side if `wi`
+    /// goes through it, or one if it's reflected.
+    pub eta: f64,
+}
+
+impl BsdfSample {
+    /// The ray leaving `hit` along `wi`, in world space, after `r` made the hit. Delta
+    /// samples carry `r`'s differentials on to keep texture filtering in reflections and
+    /// refractions.
+    pub fn ray(&self, hit: &Hit, r: &Ray) -> Ray {
+        let ray = Ray::new(hit.point, self.wi).time(r.time);
+        if !self.is_delta {
+            ray
+        } else if self.wi.dot(&hit.normal) > 0.0 {
+            ray.differential(reflect_differential(hit, r, &self.wi))
+        } else {
+            ray.differential(refract_differential(hit, r, &self.wi, 1.0 / self.eta))
+        }
+    }
+}
+
+/// How light scatters off a point of a surface, in the local shading frame where the normal
+/// is z. `wo` is the direction light leaves along and `wi` the one it arrives from, both
+/// unit vectors pointing away from the surface, and `wo` is always in front of it.
+pub trait Bxdf {
+    /// Light scattered towards `wo` from light arriving along `wi`, cosine term included.
+    /// Zero for delta directions.
+    fn eval(&self, wo: &Vec3, wi: &Vec3) -> Color;
+
+    /// Picks `wi` given `wo`, using `u_lobe` to choose between parts of the scattering
+    /// function and `u` to pick a direction within that part, all uniform in `[0, 1)`.
+    fn sample(&self, wo: &Vec3, u_lobe: f64, u: (f64, f64)) -> Option<BsdfSample>;
+
+    /// Solid angle density with which `sample` picks `wi`. Zero for delta directions.
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f64;
+
+    /// Whether every sample is a delta sample, so that sampling lights is pointless.
+    fn is_delta(&self) -> bool;
+}
+
+/// A `Bxdf` at a hit, taking and giving directions in world space.
+pub struct Bsdf {
+    frame: Frame,
+    bxdf: Box<dyn Bxdf>,
+}
+
+impl Bsdf {
+    pub fn new(frame: Frame, bxdf: Box<dyn Bxdf>) -> Self {
+        Bsdf { frame, bxdf }
+    }
+
+    pub fn eval(&self, wo: &Vec3, wi: &Vec3) -> Color {
+        let (wo, wi) = (self.frame.to_local(&wo.unit_vec()), self.frame.to_local(&wi.unit_vec()));
+        if wo.z <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        self.bxdf.eval(&wo, &wi)
+    }
+
+    pub fn sample(&self, wo: &Vec3, u_lobe: f64, u: (f64, f64)) -> Option<BsdfSample> {
+        let wo = self.frame.to_local(&wo.unit_vec());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let sample = self.bxdf.sample(&wo, u_lobe, u)?;
+        Some(BsdfSample { wi: self.frame.to_world(&sample.wi), ..sample })
+    }
+
+    pub fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        let (wo, wi) = (self.frame.to_local(&wo.unit_vec()), self.frame.to_local(&wi.unit_vec()));
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.bxdf.pdf(&wo, &wi)
+    }
+
+    pub fn is_delta(&self) -> bool {
+        self.bxdf.is_delta()
+    }
+}
+
+/// Scatters equally in every direction in front of the surface.
+pub struct LambertianBxdf {
+    pub albedo: Color,
+}
+
+impl Bxdf for LambertianBxdf {
+    fn eval(&self, _wo: &Vec3, wi: &Vec3) -> Color {
+        self.albedo * (wi.z.max(0.0) / PI)
+    }
+
+    fn sample(&self, _wo: &Vec3, _u_lobe: f64, u: (f64, f64)) -> Option<BsdfSample> {
+        let wi = cosine_hemisphere(u);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample { wi, weight: self.albedo, pdf: wi.z / PI, is_delta: false, eta: 1.0 })
+    }
+
+    fn pdf(&self, _wo: &Vec3, wi: &Vec3) -> f64 {
+        wi.z.max(0.0) / PI
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+}
+
+/// Mirror reflection off in a random direction up to `fuzz` away. Its density isn't known,
+/// so it is treated as a delta even when blurred.
+pub struct FuzzyMirrorBxdf {
+    pub albedo: Color,
+    pub fuzz: f64,
+}
+
+impl Bxdf for FuzzyMirrorBxdf {
+    fn eval(&self, _wo: &Vec3, _wi: &Vec3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    fn sample(&self, wo: &Vec3, u_lobe: f64, u: (f64, f64)) -> Option<BsdfSample> {
+        let mut wi = Vec3::new(-wo.x, -wo.y, wo.z);
+        if self.fuzz != 0.0 {
+            // A uniformly distributed point in the unit ball.
+            wi = wi + self.fuzz * u_lobe.cbrt() * uniform_sphere(u);
+        }
+        if wi.z <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample { wi: wi.unit_vec(), weight: self.albedo, pdf: 1.0, is_delta: true, eta: 1.0 })
+    }
+
+    fn pdf(&self, _wo: &Vec3, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+/// The boundary of a dielectric such as glass, rough or smooth, which reflects and
+/// transmits light in proportions given by Fresnel's equations.
+pub struct DielectricBxdf {
+    /// Index of refraction behind the surface over that in front.
+    pub eta: f64,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Bxdf for DielectricBxdf {
+    fn eval(&self, wo: &Vec3, wi: &Vec3) -> Color {
+        let f = eval_dielectric(&self.distribution, self.eta, wo, wi);
+        Color::new(f, f, f)
+    }
+
+    fn sample(&self, wo: &Vec3, u_lobe: f64, u: (f64, f64)) -> Option<BsdfSample> {
+        let (wi, weight, pdf) = sample_dielectric(&self.distribution, self.eta, wo, u, u_lobe)?;
+        Some(BsdfSample {
+            wi,
+            weight: Color::new(weight, weight, weight),
+            pdf,
+            is_delta: self.is_delta(),
+            eta: if wi.z < 0.0 { self.eta } else { 1.0 },
+        })
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        pdf_dielectric(&self.distribution, self.eta, wo, wi)
+    }
+
+    fn is_delta(&self) -> bool {
+        self.distribution.is_smooth()
+    }
+}
+
+// Differentials of perfectly specular bounces, following pbrt's derivation for the
+// shifted rays but treating the surface as locally flat, so that curvature doesn't
+// widen or narrow the footprint.
+
+fn reflect_differential(hit: &Hit, r: &Ray, wi: &Vec3) -> Option<RayDifferential> {
+    let diff = r.differential.as_ref()?;
+    let n = hit.normal;
+    let wo = -r.d.unit_vec();
+    let reflect = |d: Vec3, dpd: Vec3| {
+        let dwo = -d.unit_vec() - wo;
+        (hit.point + dpd, *wi - dwo + 2.0 * dwo.dot(&n) * n)
+    };
+    let (rx_o, rx_d) = reflect(diff.rx_d, hit.footprint.dpdx);
+    let (ry_o, ry_d) = reflect(diff.ry_d, hit.footprint.dpdy);
+    Some(RayDifferential { rx_o, rx_d, ry_o, ry_d })
+}
+
+fn refract_differential(hit: &Hit, r: &Ray, wi: &Vec3, eta: f64) -> Option<RayDifferential> {
+    let diff = r.differential.as_ref()?;
+    let n = hit.normal;
+    let wo = -r.d.unit_vec();
+    let wi = wi.unit_vec();
+    let cos_o = wo.dot(&n);
+    let cos_i = wi.dot(&n).abs();
+    let refract = |d: Vec3, dpd: Vec3| {
+        let dwo = -d.unit_vec() - wo;
+        let dmu = (eta - eta * eta * cos_o / cos_i) * dwo.dot(&n);
+        (hit.point + dpd, wi - eta * dwo + dmu * n)
+    };
+    let (rx_o, rx_d) = refract(diff.rx_d, hit.footprint.dpdx);
+    let (ry_o, ry_d) = refract(diff.ry_d, hit.footprint.dpdy);
+    Some(RayDifferential { rx_o, rx_d, ry_o, ry_d })
+}
+
+#[cfg(test)]
+mod bsdf_tests {
+    use super::*;
+    use crate::sampling::spherical;
+    use crate::material::Conductor;
+
+    #[test]
+    fn test_samples_consistent() {
+        // Non-delta samples' weights and densities agree with `eval` and `pdf`.
+        let bxdfs: Vec<Box<dyn Bxdf>> = vec![
+            Box::new(LambertianBxdf { albedo: Color::new(0.5, 0.25, 0.125) }),
+            Box::new(Conductor::gold(0.4)),
+            Box::new(DielectricBxdf { eta: 1.5, distribution: TrowbridgeReitz::new(0.2, 0.3) }),
+            Box::new(DielectricBxdf { eta: 1.0 / 1.5, distribution: TrowbridgeReitz::new(0.2, 0.3) }),
+        ];
+        let wo = spherical(0.7, 0.2);
+        for bxdf in &bxdfs {
+            for i in 0..100 {
+                let u = ((i % 10) as f64 / 10.0 + 0.05, (i / 10) as f64 / 10.0 + 0.05);
+                if let Some(sample) = bxdf.sample(&wo, (i as f64 * 0.618) % 1.0, u) {
+                    assert!(!sample.is_delta);
+                    let pdf = bxdf.pdf(&wo, &sample.wi);
+                    assert!((sample.pdf - pdf).abs() < 1e-9 * pdf);
+                    let expected = bxdf.eval(&wo, &sample.wi) * (1.0 / pdf);
+                    assert!((sample.weight.g - expected.g).abs() < 1e-6, "{:?} {:?}", sample.weight, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_delta() {
+        let mirror = FuzzyMirrorBxdf { albedo: Color::new(1.0, 1.0, 1.0), fuzz: 0.0 };
+        let wo = spherical(0.7, 0.2);
+        let sample = mirror.sample(&wo, 0.5, (0.5, 0.5)).unwrap();
+        assert!(sample.is_delta && mirror.is_delta());
+        assert!((sample.wi - Vec3::new(-wo.x, -wo.y, wo.z)).length() < 1e-12);
+        assert_eq!(mirror.pdf(&wo, &sample.wi), 0.0);
+
+        let glass = DielectricBxdf { eta: 1.5, distribution: TrowbridgeReitz::new(0.0, 0.0) };
+        let sample = glass.sample(&wo, 0.99, (0.5, 0.5)).unwrap();
+        assert!(sample.is_delta && sample.wi.z < 0.0);
+        assert_eq!(sample.eta, 1.5);
+        assert_eq!(glass.eval(&wo, &sample.wi).r, 0.0);
+    }
+}
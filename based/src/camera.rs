@@ -1,11 +1,14 @@
 use glitz::vec::Vec3;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayDifferential};
 use crate::random::with_rng;
 use rand::distributions::Uniform;
 use rand::Rng;
 
 pub trait Camera {
-    fn make_ray(&self, u: f64, v: f64) -> Ray;
+    /// Ray through the point `(u, v)` of the image, both in `[0, 1]`, carrying differentials
+    /// towards `(u + pixel.0, v)` and `(u, v + pixel.1)`.
+    /// The ray's time is spread over the shutter interval.
+    fn make_ray(&self, u: f64, v: f64, pixel: (f64, f64)) -> Ray;
 }
 
 pub struct SimpleCamera {
@@ -16,6 +19,7 @@ pub struct SimpleCamera {
     u: Vec3,
     v: Vec3,
     lens_radius: f64,
+    shutter: (f64, f64),
 }
 
 impl SimpleCamera {
@@ -41,12 +45,19 @@ impl SimpleCamera {
             v,
             lower_left: origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w,
             lens_radius: aperture / 2.0,
+            shutter: (0.0, 0.0),
         }
     }
+
+    /// Keeps the shutter open from time `open` to `close`, so that objects moving in between
+    /// are blurred. It opens and closes at time zero by default, freezing everything.
+    pub fn shutter(self, open: f64, close: f64) -> Self {
+        SimpleCamera { shutter: (open, close), ..self }
+    }
 }
 
 impl Camera for SimpleCamera {
-    fn make_ray(&self, s: f64, t: f64) -> Ray {
+    fn make_ray(&self, s: f64, t: f64, pixel: (f64, f64)) -> Ray {
 
         let range = Uniform::new_inclusive(-1.0, 1.0);
         let mut rd = [with_rng(|r| Rng::sample(r, range)), with_rng(|r| Rng::sample(r, range))];
@@ -55,11 +66,18 @@ impl Camera for SimpleCamera {
         }
 
         let offset = (self.u * rd[0] + self.v * rd[1]) * self.lens_radius;
+        let o = offset + self.origin;
+        let d = self.lower_left + s * self.horizontal + t * self.vertical - self.origin - offset;
+        let (open, close) = self.shutter;
+        let time = open + (close - open) * with_rng(|r| r.gen::<f64>());
 
-        Ray::new(
-            offset + self.origin,
-            self.lower_left + s * self.horizontal + t * self.vertical - self.origin - offset,
-        )
+        // The neighbouring rays go through the same point on the lens.
+        Ray::new(o, d).time(time).differential(Some(RayDifferential {
+            rx_o: o,
+            rx_d: d + pixel.0 * self.horizontal,
+            ry_o: o,
+            ry_d: d + pixel.1 * self.vertical,
+        }))
     }
 }
 
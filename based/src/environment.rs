@@ -0,0 +1,145 @@
+use std::f64::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::image::Image;
+use crate::import::ImportError;
+use crate::import::hdr::load_hdr;
+use crate::sampling::Distribution2D;
+
+/// What rays that escape the scene see.
+#[derive(Clone)]
+pub enum Environment {
+    /// Nothing, so escaped rays contribute no light.
+    None,
+    /// The same radiance from every direction.
+    Constant(Color),
+    /// Blends vertically from the first color straight down to the second straight up.
+    Gradient(Color, Color),
+    /// An image wrapped around the scene, which also acts as a light.
+    Map(Arc<EnvironmentMap>),
+}
+
+impl Environment {
+    /// The classic sky, white at the horizon fading into light blue overhead.
+    pub fn sky() -> Self {
+        Environment::Gradient(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+
+    /// Radiance arriving from direction `d`, which need not be normalized.
+    pub fn radiance(&self, d: &Vec3) -> Color {
+        match self {
+            Environment::None => Color::new(0.0, 0.0, 0.0),
+            Environment::Constant(color) => *color,
+            Environment::Gradient(bottom, top) => {
+                let t = 0.5 * (d.unit_vec().y + 1.0);
+                (1.0 - t) * *bottom + t * *top
+            }
+            Environment::Map(map) => map.radiance(d),
+        }
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::sky()
+    }
+}
+
+/// Equirectangular image covering every direction, with +y at the top row and the middle
+/// column looking down +x. Directions are importance sampled by their brightness.
+pub struct EnvironmentMap {
+    image: Image,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Image) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let mut func = Vec::with_capacity(width * height);
+        for y in 0..height {
+            // Rows near the poles cover less solid angle, so they're worth less.
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            func.extend((0..width).map(|x| image.get(x, y).luminance().max(0.0) * sin_theta));
+        }
+        let distribution = Distribution2D::new(&func, width, height);
+        EnvironmentMap { image, distribution }
+    }
+
+    /// Loads a Radiance `.hdr` image as an environment map.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        Ok(Self::new(load_hdr(path)?))
+    }
+
+    pub fn radiance(&self, d: &Vec3) -> Color {
+        let (u, v) = direction_to_uv(d);
+        let x = ((u * self.image.width() as f64) as usize).min(self.image.width() - 1);
+        let y = ((v * self.image.height() as f64) as usize).min(self.image.height() - 1);
+        self.image.get(x, y)
+    }
+
+    /// Picks a direction with probability roughly proportional to its brightness, returning
+    /// the unit direction, the radiance arriving from it and its solid angle density.
+    pub fn sample(&self, u: (f64, f64)) -> Option<(Vec3, Color, f64)> {
+        let (uv, pdf_uv) = self.distribution.sample(u);
+        let sin_theta = (PI * uv.1).sin();
+        if pdf_uv == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+        let d = uv_to_direction(uv);
+        Some((d, self.radiance(&d), pdf_uv / (2.0 * PI * PI * sin_theta)))
+    }
+
+    /// Solid angle density with which `sample` picks direction `d`.
+    pub fn pdf(&self, d: &Vec3) -> f64 {
+        let uv = direction_to_uv(d);
+        let sin_theta = (PI * uv.1).sin();
+        if sin_theta == 0.0 {
+            0.0
+        } else {
+            self.distribution.pdf(uv) / (2.0 * PI * PI * sin_theta)
+        }
+    }
+}
+
+fn direction_to_uv(d: &Vec3) -> (f64, f64) {
+    let d = d.unit_vec();
+    let theta = d.y.clamp(-1.0, 1.0).acos();
+    let phi = (-d.z).atan2(d.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+fn uv_to_direction((u, v): (f64, f64)) -> Vec3 {
+    let theta = v * PI;
+    let phi = u * 2.0 * PI - PI;
+    Vec3::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin())
+}
+
+#[cfg(test)]
+mod environment_tests {
+    use super::*;
+
+    #[test]
+    fn test_uv_round_trip() {
+        for &d in [Vec3::new(1.0, 0.2, 0.3), Vec3::new(-0.5, -0.7, 0.1), Vec3::new(0.0, 0.3, -1.0)].iter() {
+            let back = uv_to_direction(direction_to_uv(&d));
+            assert!((back - d.unit_vec()).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_sample_matches_pdf() {
+        let mut pixels = vec![Color::new(0.1, 0.1, 0.1); 8 * 4];
+        pixels[8 + 5] = Color::new(50.0, 40.0, 30.0);
+        let map = EnvironmentMap::new(Image::new(8, 4, pixels));
+        for &u in [(0.1, 0.2), (0.7, 0.5), (0.99, 0.9)].iter() {
+            let (d, radiance, pdf) = map.sample(u).unwrap();
+            assert!((map.pdf(&d) - pdf).abs() < 1e-9 * pdf);
+            assert_eq!(radiance.r, map.radiance(&d).r);
+        }
+        // Most samples should land on the bright texel.
+        let (d, _, _) = map.sample((0.5, 0.5)).unwrap();
+        assert_eq!(map.radiance(&d).r, 50.0);
+    }
+}
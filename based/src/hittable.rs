@@ -1,29 +1,209 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::sync::Arc;
 use glitz::vec::Vec3;
+use glitz::aabb::Aabb;
+use glitz::frame::Frame;
 use crate::ray::Ray;
 use crate::material::Material;
+use crate::medium::Medium;
+use crate::texture::Footprint;
+
+pub mod bvh;
+pub mod instance;
+pub mod mesh;
+pub mod shapes;
+pub mod volume;
 
 pub struct Hit<'a> {
     pub point: Vec3,
     pub normal: Vec3,
     pub t: f64,
     pub front_face: bool,
+    pub uv: (f64, f64),
+    /// Partial derivatives of the point with respect to `uv`, zero where the primitive doesn't provide them.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    /// Area around the point that the ray stands for, zero until `compute_footprint` is called.
+    pub footprint: Footprint,
+    /// Time of the ray that made the hit, zero until `Scene::intersect` copies it over.
+    pub time: f64,
+    /// Medium filling the inside of the surface, if it bounds a `Volume`.
+    pub medium: Option<&'a dyn Medium>,
+    /// Whether the primitive hit is one that `collect_lights` gathers, so that sampling the
+    /// lights could have picked this point too.
+    pub is_light: bool,
+    /// Identifies the object hit, built from the number each `Bvh` or `HittableList` on the
+    /// way down gave the primitive the hit came through, and zero if there were none.
+    pub object: u64,
     pub mat: &'a Material,
 }
 
 impl<'a> Hit<'a> {
-    pub fn new(point: Vec3, normal: Vec3, t: f64, front_face: bool, mat: &'a Material) -> Hit<'a> {
+    pub fn new(point: Vec3, normal: Vec3, t: f64, front_face: bool, uv: (f64, f64), mat: &'a Material) -> Hit<'a> {
         Hit {
             point, 
             normal,
             t,
             front_face,
+            uv,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            footprint: Footprint::default(),
+            time: 0.0,
+            medium: None,
+            is_light: false,
+            object: 0,
             mat,
         }
     }
+
+    pub fn tangents(self, dpdu: Vec3, dpdv: Vec3) -> Self {
+        Hit { dpdu, dpdv, ..self }
+    }
+
+    pub fn light(self, is_light: bool) -> Self {
+        Hit { is_light, ..self }
+    }
+
+    /// Records that the hit came through the object numbered `id` within some collection, on
+    /// top of whatever collections within that object numbered it.
+    pub fn within(self, id: u32) -> Self {
+        // Odd multiplier, so that different paths down nested collections rarely coincide.
+        Hit { object: self.object.wrapping_mul(0x100000001b3).wrapping_add(id as u64 + 1), ..self }
+    }
+
+    /// Local frame around the normal, with its first tangent along `dpdu` where there is one.
+    pub fn shading_frame(&self) -> Frame {
+        Frame::from_normal_tangent(self.normal, self.dpdu)
+    }
+
+    /// Finds where the differentials of `r`, the ray that made this hit, meet the tangent plane
+    /// at the hit point, and from that how far the point and its texture coordinates move
+    /// from one pixel to the next.
+    pub fn compute_footprint(&mut self, r: &Ray) {
+        let diff = match &r.differential {
+            Some(diff) => diff,
+            None => return,
+        };
+        let n = self.normal;
+        let plane_dist = n.dot(&self.point);
+        let tx = (plane_dist - n.dot(&diff.rx_o)) / n.dot(&diff.rx_d);
+        let ty = (plane_dist - n.dot(&diff.ry_o)) / n.dot(&diff.ry_d);
+        if !tx.is_finite() || !ty.is_finite() {
+            return;
+        }
+        let dpdx = diff.rx_o + tx * diff.rx_d - self.point;
+        let dpdy = diff.ry_o + ty * diff.ry_d - self.point;
+
+        // Solve dp = du * dpdu + dv * dpdv in least squares fashion, by dropping whichever
+        // axis the normal is closest to, as the tangents vary least along it.
+        let (a, b) = if n.x.abs() > n.y.abs() && n.x.abs() > n.z.abs() {
+            (1, 2)
+        } else if n.y.abs() > n.z.abs() {
+            (0, 2)
+        } else {
+            (0, 1)
+        };
+        let det = self.dpdu[a] * self.dpdv[b] - self.dpdv[a] * self.dpdu[b];
+        let solve = |d: Vec3| {
+            if det.abs() < 1e-12 {
+                (0.0, 0.0)
+            } else {
+                let du = (self.dpdv[b] * d[a] - self.dpdv[a] * d[b]) / det;
+                let dv = (self.dpdu[a] * d[b] - self.dpdu[b] * d[a]) / det;
+                if du.is_finite() && dv.is_finite() { (du, dv) } else { (0.0, 0.0) }
+            }
+        };
+        self.footprint = Footprint { dpdx, dpdy, duvdx: solve(dpdx), duvdy: solve(dpdy) };
+    }
+}
+
+/// Anything a ray can be intersected with.
+pub trait Hittable: Send + Sync {
+    /// Closest intersection with `r` whose `t` lies in `[tmin, tmax]`, if any.
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>>;
+
+    /// Box enclosing everything `intersect` can hit.
+    fn bounding_box(&self) -> Aabb;
+
+    /// Surface area, for primitives that can be sampled as lights.
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    /// Uniformly picks a point on the surface given two uniform numbers, returned as a `Hit`
+    /// with an outward facing normal. Primitives that can't be sampled return `None`.
+    fn sample_surface(&self, _u: (f64, f64)) -> Option<Hit<'_>> {
+        None
+    }
+
+    /// Appends every emissive primitive within this object to `lights`.
+    fn collect_lights<'a>(&'a self, _lights: &mut Vec<&'a dyn Hittable>) {}
+
+    /// Address of the object this is part of. Collections give primitives with the same key
+    /// the same number, so the triangles of a mesh count as one object.
+    fn object_key(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        (**self).intersect(r, tmin, tmax)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn area(&self) -> f64 {
+        (**self).area()
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        (**self).sample_surface(u)
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        (**self).collect_lights(lights)
+    }
+
+    fn object_key(&self) -> usize {
+        (**self).object_key()
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        (**self).intersect(r, tmin, tmax)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn area(&self) -> f64 {
+        (**self).area()
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        (**self).sample_surface(u)
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        (**self).collect_lights(lights)
+    }
+
+    fn object_key(&self) -> usize {
+        (**self).object_key()
+    }
 }
 
 pub struct Sphere {
     center: Vec3,
+    /// How far the center moves between times zero and one.
+    motion: Vec3,
     radius: f64,
     mat: Material,
 }
@@ -32,13 +212,46 @@ impl Sphere {
     pub fn new(center: Vec3, radius: f64, mat: Material) -> Sphere {
         Sphere {
             center,
+            motion: Vec3::default(),
             radius,
             mat,
         }
     }
 
-    pub fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit> {
-        let oc = r.o - self.center;
+    /// A sphere moving in a straight line from `from` at time zero to `to` at time one, and
+    /// resting there before and after. Moving spheres aren't sampled as lights.
+    pub fn moving(from: Vec3, to: Vec3, radius: f64, mat: Material) -> Sphere {
+        Sphere { motion: to - from, ..Sphere::new(from, radius, mat) }
+    }
+
+    fn center(&self, time: f64) -> Vec3 {
+        self.center + time.clamp(0.0, 1.0) * self.motion
+    }
+
+    // Maps a point on the unit sphere to (longitude, latitude), both in [0, 1].
+    fn uv(p: &Vec3) -> (f64, f64) {
+        let theta = (-p.y).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    // Derivatives of the point at unit sphere point `p` with respect to the coordinates from
+    // `uv`, zero at the poles where they are undefined.
+    fn tangents(&self, p: &Vec3) -> (Vec3, Vec3) {
+        let ring = (p.x * p.x + p.z * p.z).sqrt();
+        if ring < 1e-9 {
+            return (Vec3::default(), Vec3::default());
+        }
+        let dpdu = 2.0 * PI * self.radius * Vec3::new(p.z, 0.0, -p.x);
+        let dpdv = PI * self.radius * Vec3::new(-p.y * p.x / ring, ring, -p.y * p.z / ring);
+        (dpdu, dpdv)
+    }
+}
+
+impl Hittable for Sphere {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let center = self.center(r.time);
+        let oc = r.o - center;
         let a = r.d.dot(&r.d);
         let half_b = oc.dot(&r.d);
         let c = oc.dot(&oc) - self.radius * self.radius;
@@ -56,24 +269,140 @@ impl Sphere {
                 }
             }
             let point = r.at(root);
-            let outward_normal = (point - self.center) / self.radius;
+            let outward_normal = (point - center) / self.radius;
             let front_face = r.d.dot(&outward_normal) < 0.0;
             let normal = if front_face { outward_normal } else { -outward_normal };
-            Some(Hit::new(point, normal, root, front_face, &self.mat))
+            let (dpdu, dpdv) = self.tangents(&outward_normal);
+            Some(Hit::new(point, normal, root, front_face, Self::uv(&outward_normal), &self.mat)
+                .tangents(dpdu, dpdv)
+                .light(self.motion == Vec3::default()))
         } else {
             None
         }
     }
+
+    fn bounding_box(&self) -> Aabb {
+        // Covers the whole path, so that a `Bvh` finds the sphere whenever a ray is fired.
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        let end = self.center + self.motion;
+        Aabb::new(self.center - radius, self.center + radius).union(&Aabb::new(end - radius, end + radius))
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        let z = 1.0 - 2.0 * u.0;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        let outward_normal = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+        let point = self.center + self.radius * outward_normal;
+        Some(Hit::new(point, outward_normal, 0.0, true, Self::uv(&outward_normal), &self.mat).light(self.motion == Vec3::default()))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mat.is_emissive() && self.motion == Vec3::default() {
+            lights.push(self);
+        }
+    }
+}
+
+/// A heterogeneous collection of objects, intersected by testing each in turn.
+#[derive(Default)]
+pub struct HittableList {
+    objects: Vec<Box<dyn Hittable>>,
+    // Number of the object each of `objects` is part of, in the order they were added.
+    ids: Vec<u32>,
+    numbers: HashMap<usize, u32>,
 }
 
-pub fn intersect_spheres<'a>(list: &'a [Sphere], r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'a>> {
-    let mut result = None;
-    let mut closest_so_far = tmax;
-    for obj in list {
-        if let Some(ray_hit) = obj.intersect(r, tmin, closest_so_far) {
-            closest_so_far = ray_hit.t;
-            result = Some(ray_hit);
+impl HittableList {
+    pub fn new() -> HittableList {
+        HittableList::default()
+    }
+
+    pub fn add(&mut self, object: impl Hittable + 'static) {
+        let object: Box<dyn Hittable> = Box::new(object);
+        let next = self.numbers.len() as u32;
+        self.ids.push(*self.numbers.entry(object.object_key()).or_insert(next));
+        self.objects.push(object);
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn objects(&self) -> &[Box<dyn Hittable>] {
+        &self.objects
+    }
+
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
+}
+
+impl Hittable for HittableList {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let mut result = None;
+        let mut closest_so_far = tmax;
+        for (obj, &id) in self.objects.iter().zip(&self.ids) {
+            if let Some(ray_hit) = obj.intersect(r, tmin, closest_so_far) {
+                closest_so_far = ray_hit.t;
+                result = Some(ray_hit.within(id));
+            }
         }
+        result
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::EMPTY, |b, obj| b.union(&obj.bounding_box()))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        for obj in &self.objects {
+            obj.collect_lights(lights);
+        }
+    }
+}
+
+#[cfg(test)]
+mod hittable_tests {
+    use super::*;
+    use std::sync::Arc;
+    use xenon::color::Color;
+
+    #[test]
+    fn test_sphere_tangents() {
+        let sphere = Sphere::new(Vec3::new(1.0, 2.0, 3.0), 2.0, Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5))));
+        let n = Vec3::new(0.3, -0.5, 0.6).unit_vec();
+        let (dpdu, dpdv) = sphere.tangents(&n);
+        let (u, v) = Sphere::uv(&n);
+        // Stepping along each tangent moves the coordinates by the step.
+        let eps = 1e-6;
+        let (u1, v1) = Sphere::uv(&(n + eps * dpdu / sphere.radius).unit_vec());
+        assert!(((u1 - u) / eps - 1.0).abs() < 1e-4 && ((v1 - v) / eps).abs() < 1e-4);
+        let (u2, v2) = Sphere::uv(&(n + eps * dpdv / sphere.radius).unit_vec());
+        assert!(((u2 - u) / eps).abs() < 1e-4 && ((v2 - v) / eps - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_moving_sphere() {
+        let sphere = Sphere::moving(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 4.0, 0.0), 1.0, Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5))));
+        let bounds = sphere.bounding_box();
+        assert_eq!((bounds.min, bounds.max), (Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 5.0, 1.0)));
+
+        let r = Ray::new(Vec3::new(0.0, 3.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(sphere.intersect(&r, 0.0, f64::INFINITY).is_none());
+        let hit = sphere.intersect(&r.time(0.75), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-12 && (hit.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+        // Isn't among the lights, so its hits mustn't claim to be.
+        assert!(!hit.is_light);
+        // Rests at the end of its path afterwards.
+        assert!(sphere.intersect(&Ray::new(Vec3::new(0.0, 4.0, -5.0), Vec3::new(0.0, 0.0, 1.0)).time(2.0), 0.0, f64::INFINITY).is_some());
     }
-    result
 }
@@ -0,0 +1,310 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use glitz::vec::Vec3;
+use glitz::aabb::Aabb;
+use crate::ray::Ray;
+use crate::hittable::{Hit, Hittable};
+
+const NUM_BUCKETS: usize = 12;
+const MAX_PRIMS_IN_LEAF: usize = 4;
+const MAX_DEPTH: usize = 64;
+// Below this depth splits are at the median, which with at most `u32::MAX` primitives
+// reaches single ones before `MAX_DEPTH` however unevenly the SAH split above.
+const MEDIAN_SPLIT_DEPTH: usize = MAX_DEPTH - 32;
+
+// Relative cost of traversing a node versus intersecting a primitive, used by the SAH.
+const TRAVERSAL_COST: f64 = 0.125;
+
+// Nodes are stored depth-first, so an interior node's first child directly follows it
+// and only the second child's index needs to be recorded.
+#[derive(Clone, Copy)]
+struct LinearNode {
+    bounds: Aabb,
+    // Index of the first primitive for leaves, of the second child for interior nodes.
+    offset: u32,
+    num_prims: u16,
+    axis: u8,
+}
+
+struct BuildPrim {
+    index: usize,
+    bounds: Aabb,
+    centroid: Vec3,
+}
+
+#[thread_local]
+static NODES_VISITED: Cell<u64> = Cell::new(0);
+
+/// Number of nodes of any `Bvh` this thread has tested rays against so far. Differences
+/// between two calls measure the cost of the intersections in between.
+pub fn nodes_visited() -> u64 {
+    NODES_VISITED.get()
+}
+
+/// Bounding volume hierarchy over a set of primitives, built with the surface area heuristic.
+pub struct Bvh<T: Hittable = Box<dyn Hittable>> {
+    prims: Vec<T>,
+    // Number of the object each primitive is part of, see `Hit::object`.
+    ids: Vec<u32>,
+    nodes: Vec<LinearNode>,
+}
+
+impl<T: Hittable> Bvh<T> {
+    pub fn new(prims: Vec<T>) -> Self {
+        // Objects are numbered in the order they're given rather than the order the build
+        // leaves them in, so the numbers don't change with how the tree was split.
+        let mut numbers = HashMap::new();
+        let ids = prims.iter().map(|p| {
+            let next = numbers.len() as u32;
+            *numbers.entry(p.object_key()).or_insert(next)
+        }).collect::<Vec<_>>();
+
+        let mut build_prims = prims.iter().enumerate().map(|(index, p)| {
+            let bounds = p.bounding_box();
+            BuildPrim { index, bounds, centroid: bounds.centroid() }
+        }).collect::<Vec<_>>();
+
+        let mut nodes = Vec::with_capacity(2 * prims.len());
+        if !build_prims.is_empty() {
+            Self::build(&mut build_prims, 0, 0, &mut nodes);
+        }
+
+        // Reorder primitives so every leaf references a contiguous range.
+        let mut slots = prims.into_iter().map(Some).collect::<Vec<_>>();
+        let prims = build_prims.iter().map(|p| slots[p.index].take().unwrap()).collect();
+        let ids = build_prims.iter().map(|p| ids[p.index]).collect();
+
+        Bvh { prims, ids, nodes }
+    }
+
+    pub fn primitives(&self) -> &[T] {
+        &self.prims
+    }
+
+    /// Primitives for changing in place, after which `refit` must be called before the
+    /// hierarchy is used again.
+    pub fn primitives_mut(&mut self) -> &mut [T] {
+        &mut self.prims
+    }
+
+    /// Recomputes every node's bounds from its primitives, keeping the structure of the tree.
+    /// Much cheaper than building anew when primitives have moved, such as instances whose
+    /// transforms changed, but the further they stray from where they were when it was built
+    /// the slower the tree gets to traverse.
+    pub fn refit(&mut self) {
+        // Children always come after their parents, so going backwards visits them first.
+        for i in (0..self.nodes.len()).rev() {
+            let node = self.nodes[i];
+            let offset = node.offset as usize;
+            self.nodes[i].bounds = if node.num_prims > 0 {
+                self.prims[offset..offset + node.num_prims as usize].iter()
+                    .fold(Aabb::EMPTY, |b, p| b.union(&p.bounding_box()))
+            } else {
+                self.nodes[i + 1].bounds.union(&self.nodes[offset].bounds)
+            };
+        }
+    }
+
+    // Builds the subtree over `prims`, which start at `offset` in the final primitive order,
+    // with its root `depth` levels down.
+    fn build(prims: &mut [BuildPrim], offset: usize, depth: usize, nodes: &mut Vec<LinearNode>) {
+        let bounds = prims.iter().fold(Aabb::EMPTY, |b, p| b.union(&p.bounds));
+        let node_index = nodes.len();
+        nodes.push(LinearNode { bounds, offset: offset as u32, num_prims: prims.len() as u16, axis: 0 });
+
+        if prims.len() == 1 {
+            return;
+        }
+
+        let centroid_bounds = prims.iter().fold(Aabb::EMPTY, |b, p| b.union_point(&p.centroid));
+        let axis = centroid_bounds.longest_axis();
+        if centroid_bounds.max[axis] == centroid_bounds.min[axis] {
+            // All centroids coincide, so no split can separate them.
+            if prims.len() <= u16::MAX as usize {
+                return;
+            }
+            let mid = prims.len() / 2;
+            return Self::build_interior(prims, mid, axis, offset, depth, node_index, nodes);
+        }
+
+        let mid = if prims.len() <= 2 || depth >= MEDIAN_SPLIT_DEPTH {
+            let mid = prims.len() / 2;
+            prims.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+            mid
+        } else {
+            let bucket_of = |p: &BuildPrim| {
+                let b = (NUM_BUCKETS as f64 * centroid_bounds.offset(&p.centroid)[axis]) as usize;
+                b.min(NUM_BUCKETS - 1)
+            };
+
+            let mut counts = [0usize; NUM_BUCKETS];
+            let mut bucket_bounds = [Aabb::EMPTY; NUM_BUCKETS];
+            for p in prims.iter() {
+                let b = bucket_of(p);
+                counts[b] += 1;
+                bucket_bounds[b] = bucket_bounds[b].union(&p.bounds);
+            }
+
+            // Sweep from both ends to get the cost of splitting after each bucket in linear time.
+            let mut costs = [0.0; NUM_BUCKETS - 1];
+            let mut count_below = 0;
+            let mut bounds_below = Aabb::EMPTY;
+            for i in 0..NUM_BUCKETS - 1 {
+                count_below += counts[i];
+                bounds_below = bounds_below.union(&bucket_bounds[i]);
+                costs[i] = count_below as f64 * bounds_below.surface_area();
+            }
+            let mut count_above = 0;
+            let mut bounds_above = Aabb::EMPTY;
+            for i in (1..NUM_BUCKETS).rev() {
+                count_above += counts[i];
+                bounds_above = bounds_above.union(&bucket_bounds[i]);
+                costs[i - 1] += count_above as f64 * bounds_above.surface_area();
+            }
+
+            let (min_bucket, min_cost) = costs.iter().enumerate()
+                .fold((0, f64::INFINITY), |best, (i, &c)| if c < best.1 { (i, c) } else { best });
+            let leaf_cost = prims.len() as f64;
+            let split_cost = TRAVERSAL_COST + min_cost / bounds.surface_area();
+
+            if prims.len() <= MAX_PRIMS_IN_LEAF && leaf_cost <= split_cost {
+                return;
+            }
+
+            let mut mid = 0;
+            for i in 0..prims.len() {
+                if bucket_of(&prims[i]) <= min_bucket {
+                    prims.swap(i, mid);
+                    mid += 1;
+                }
+            }
+            mid
+        };
+
+        Self::build_interior(prims, mid, axis, offset, depth, node_index, nodes);
+    }
+
+    fn build_interior(prims: &mut [BuildPrim], mid: usize, axis: usize, offset: usize, depth: usize, node_index: usize, nodes: &mut Vec<LinearNode>) {
+        let (left, right) = prims.split_at_mut(mid);
+        Self::build(left, offset, depth + 1, nodes);
+        let second_child = nodes.len();
+        Self::build(right, offset + mid, depth + 1, nodes);
+
+        let node = &mut nodes[node_index];
+        node.offset = second_child as u32;
+        node.num_prims = 0;
+        node.axis = axis as u8;
+    }
+}
+
+impl<T: Hittable> Hittable for Bvh<T> {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inv_d = Vec3::new(1.0 / r.d.x, 1.0 / r.d.y, 1.0 / r.d.z);
+        let dir_is_neg = [inv_d.x < 0.0, inv_d.y < 0.0, inv_d.z < 0.0];
+
+        let mut result = None;
+        let mut closest_so_far = tmax;
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut stack_size = 0;
+        let mut current = 0;
+        let mut visited = 0;
+        loop {
+            let node = &self.nodes[current];
+            visited += 1;
+            if node.bounds.intersect(&r.o, &inv_d, tmin, closest_so_far) {
+                if node.num_prims > 0 {
+                    let leaf = node.offset as usize..node.offset as usize + node.num_prims as usize;
+                    for (prim, &id) in self.prims[leaf.clone()].iter().zip(&self.ids[leaf]) {
+                        if let Some(hit) = prim.intersect(r, tmin, closest_so_far) {
+                            closest_so_far = hit.t;
+                            result = Some(hit.within(id));
+                        }
+                    }
+                } else {
+                    // Visit the child nearer along the ray first so `closest_so_far` shrinks sooner.
+                    if dir_is_neg[node.axis as usize] {
+                        stack[stack_size] = current + 1;
+                        current = node.offset as usize;
+                    } else {
+                        stack[stack_size] = node.offset as usize;
+                        current += 1;
+                    }
+                    stack_size += 1;
+                    continue;
+                }
+            }
+            if stack_size == 0 {
+                break;
+            }
+            stack_size -= 1;
+            current = stack[stack_size];
+        }
+        NODES_VISITED.set(NODES_VISITED.get() + visited);
+        result
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |n| n.bounds)
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        for prim in &self.prims {
+            prim.collect_lights(lights);
+        }
+    }
+}
+
+#[cfg(test)]
+mod bvh_tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::hittable::{HittableList, Sphere};
+    use crate::material::Material;
+    use xenon::color::Color;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256Plus;
+
+    #[test]
+    fn test_matches_linear_scan() {
+        let mut rng = Xoshiro256Plus::seed_from_u64(0);
+        let mut list = HittableList::new();
+        let mut spheres = Vec::new();
+        for _ in 0..500 {
+            let center = 10.0 * rng.gen::<Vec3>();
+            let radius = rng.gen_range(0.05..0.5);
+            list.add(Sphere::new(center, radius, Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))));
+            spheres.push(Sphere::new(center, radius, Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))));
+        }
+        let bvh = Bvh::new(spheres);
+        assert_eq!(bvh.bounding_box(), list.bounding_box());
+
+        for _ in 0..1000 {
+            let r = Ray::new(15.0 * rng.gen::<Vec3>(), rng.gen());
+            // Both number the spheres in the order they were given.
+            let expected = list.intersect(&r, 0.001, f64::INFINITY).map(|h| (h.t, h.object));
+            let actual = bvh.intersect(&r, 0.001, f64::INFINITY).map(|h| (h.t, h.object));
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        // Each primitive sixteen times further out than the last, so that every split by the
+        // SAH only peels off the outermost one.
+        let mat = || Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)));
+        let spheres = (0..100).map(|i| {
+            let x = 16f64.powi(i);
+            Sphere::new(Vec3::new(x, 0.0, 0.0), 0.1 * x, mat())
+        }).collect::<Vec<_>>();
+        let bvh = Bvh::new(spheres);
+        for i in 0..100 {
+            let x = 16f64.powi(i);
+            let hit = bvh.intersect(&Ray::new(Vec3::new(x, x, 0.0), Vec3::new(0.0, -1.0, 0.0)), 0.0, f64::INFINITY).unwrap();
+            assert!((hit.t - 0.9 * x).abs() < 1e-9 * x);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use glitz::aabb::Aabb;
+use glitz::transform::{AnimatedTransform, Transform};
+use crate::hittable::{Hit, Hittable};
+use crate::ray::{Ray, RayDifferential};
+
+/// An object placed in the scene by a transform from its own space. Many instances can
+/// share one object, such as a mesh with its own `Bvh`, so that geometry is stored once
+/// however many times it appears. A `Bvh` over instances makes a two-level hierarchy, whose
+/// top level can be refit cheaply when only the transforms change.
+///
+/// Instances aren't sampled as lights, so emissive objects inside them only light the scene
+/// through rays that happen to hit them, which makes for more noise than a light that is.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    bounds: Aabb,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let bounds = transform.bounds(&object.bounding_box());
+        Instance { object, transform, bounds }
+    }
+
+    pub fn object(&self) -> &Arc<dyn Hittable> {
+        &self.object
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Moves the instance. Any `Bvh` containing it needs to be refit afterwards.
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.bounds = transform.bounds(&self.object.bounding_box());
+        self.transform = transform;
+    }
+}
+
+impl Hittable for Instance {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        intersect_transformed(&*self.object, &self.transform, r, tmin, tmax)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+/// An instance that moves, placed wherever its animation has it at the time of each ray.
+/// Its bounds cover the whole animation.
+pub struct AnimatedInstance {
+    object: Arc<dyn Hittable>,
+    animation: AnimatedTransform,
+    bounds: Aabb,
+}
+
+impl AnimatedInstance {
+    pub fn new(object: Arc<dyn Hittable>, animation: AnimatedTransform) -> Self {
+        let bounds = animation.bounds(&object.bounding_box());
+        AnimatedInstance { object, animation, bounds }
+    }
+
+    pub fn object(&self) -> &Arc<dyn Hittable> {
+        &self.object
+    }
+
+    pub fn animation(&self) -> &AnimatedTransform {
+        &self.animation
+    }
+}
+
+impl Hittable for AnimatedInstance {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        intersect_transformed(&*self.object, &self.animation.at(r.time), r, tmin, tmax)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+/// Intersects `r` with `object` placed by `transform` from its own space.
+fn intersect_transformed<'a>(object: &'a dyn Hittable, transform: &Transform, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'a>> {
+    // Directions aren't renormalized, so distances along the ray stay the same in both spaces.
+    let to_object = transform.inverse();
+    let differential = r.differential.map(|diff| RayDifferential {
+        rx_o: to_object.point(&diff.rx_o),
+        rx_d: to_object.vector(&diff.rx_d),
+        ry_o: to_object.point(&diff.ry_o),
+        ry_d: to_object.vector(&diff.ry_d),
+    });
+    let object_ray = Ray::new(to_object.point(&r.o), to_object.vector(&r.d)).time(r.time).differential(differential);
+
+    let mut hit = object.intersect(&object_ray, tmin, tmax)?;
+    hit.point = transform.point(&hit.point);
+    hit.normal = transform.normal(&hit.normal).unit_vec();
+    hit.dpdu = transform.vector(&hit.dpdu);
+    hit.dpdv = transform.vector(&hit.dpdv);
+    // The object's own light samples would be in the wrong place, so it's never among the lights.
+    hit.is_light = false;
+    Some(hit)
+}
+
+#[cfg(test)]
+mod instance_tests {
+    use super::*;
+    use glitz::vec::Vec3;
+    use xenon::color::Color;
+    use glitz::transform::Keyframe;
+    use crate::hittable::Sphere;
+    use crate::hittable::bvh::Bvh;
+    use crate::hittable::mesh::TriangleMesh;
+    use crate::material::Material;
+
+    fn unit_sphere() -> Arc<dyn Hittable> {
+        Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))))
+    }
+
+    #[test]
+    fn test_transformed_hit() {
+        // Squashed into an ellipsoid half as tall, then moved up.
+        let transform = Transform::scale(Vec3::new(1.0, 0.5, 1.0)).then(&Transform::translate(Vec3::new(0.0, 3.0, 0.0)));
+        let instance = Instance::new(unit_sphere(), transform);
+        let bounds = instance.bounding_box();
+        assert!((bounds.min - Vec3::new(-1.0, 2.5, -1.0)).length() < 1e-12);
+
+        let r = Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -2.0, 0.0));
+        let hit = instance.intersect(&r, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.point - Vec3::new(0.0, 3.5, 0.0)).length() < 1e-12);
+        assert!((hit.point - r.at(hit.t)).length() < 1e-12);
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-12);
+
+        // Off the pole, the normal tilts more steeply than the sphere's would.
+        let r = Ray::new(Vec3::new(0.6, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = instance.intersect(&r, 0.0, f64::INFINITY).unwrap();
+        let expected = Vec3::new(hit.point.x, 4.0 * (hit.point.y - 3.0), 0.0).unit_vec();
+        assert!((hit.normal - expected).length() < 1e-9);
+        assert!(instance.intersect(&Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(1.0, 0.0, 0.0)), 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_refit_two_levels() {
+        // A row of instances of one sphere, then moved alternately up and down.
+        let sphere = unit_sphere();
+        let place = |i: usize, lift: f64| Transform::rotate(Vec3::new(0.0, 1.0, 0.0), i as f64)
+            .then(&Transform::translate(Vec3::new(3.0 * i as f64, lift, 0.0)));
+        let lift = |i: usize| if i.is_multiple_of(2) { 5.0 } else { -5.0 };
+        let mut tlas = Bvh::new((0..50).map(|i| Instance::new(sphere.clone(), place(i, 0.0))).collect());
+        for instance in tlas.primitives_mut() {
+            let i = (instance.transform().point(&Vec3::default()).x / 3.0).round() as usize;
+            instance.set_transform(place(i, lift(i)));
+        }
+        tlas.refit();
+
+        for i in 0..50 {
+            for &y in &[5.0, -5.0] {
+                let r = Ray::new(Vec3::new(3.0 * i as f64, y, -10.0), Vec3::new(0.0, 0.0, 1.0));
+                let hit = tlas.intersect(&r, 0.0, f64::INFINITY);
+                assert_eq!(hit.is_some(), y == lift(i));
+                assert!(hit.is_none_or(|hit| (hit.t - 9.0).abs() < 1e-9));
+            }
+        }
+        let bounds = tlas.bounding_box();
+        assert!((bounds.min.y + 6.0).abs() < 1e-12 && (bounds.max.y - 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_object_ids() {
+        // A unit square of two triangles placed twice, and two spheres sharing a material.
+        let positions = vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        let mat = Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)));
+        let square: Arc<dyn Hittable> = Arc::new(Bvh::new(TriangleMesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], mat.clone()).into_triangles()));
+        let scene: Bvh = Bvh::new(vec![
+            Box::new(Instance::new(square.clone(), Transform::translate(Vec3::new(0.0, 0.0, 0.0)))),
+            Box::new(Instance::new(square, Transform::translate(Vec3::new(10.0, 0.0, 0.0)))),
+            Box::new(Sphere::new(Vec3::new(20.0, 0.5, 0.0), 0.5, mat.clone())),
+            Box::new(Sphere::new(Vec3::new(30.0, 0.5, 0.0), 0.5, mat)),
+        ]);
+        let object = |x: f64, y: f64| scene.intersect(&Ray::new(Vec3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0)), 0.0, f64::INFINITY).unwrap().object;
+
+        assert_eq!(object(0.75, 0.25), object(0.25, 0.75));
+        assert_ne!(object(0.75, 0.25), object(10.75, 0.25));
+        assert_eq!(object(10.75, 0.25), object(10.25, 0.75));
+        assert_ne!(object(20.0, 0.5), object(30.0, 0.5));
+    }
+
+    #[test]
+    fn test_animated_hit() {
+        // Slides from x = 0 to x = 10 over the shutter, passing under a ray at x = 5 halfway.
+        let animation = AnimatedTransform::new(vec![Keyframe::new(0.0), Keyframe::new(1.0).translation(Vec3::new(10.0, 0.0, 0.0))]);
+        let instance = AnimatedInstance::new(unit_sphere(), animation);
+        let bounds = instance.bounding_box();
+        assert!((bounds.min - Vec3::new(-1.0, -1.0, -1.0)).length() < 1e-12 && (bounds.max - Vec3::new(11.0, 1.0, 1.0)).length() < 1e-12);
+
+        let r = Ray::new(Vec3::new(5.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(instance.intersect(&r, 0.0, f64::INFINITY).is_none());
+        let hit = instance.intersect(&r.time(0.5), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.point - Vec3::new(5.0, 1.0, 0.0)).length() < 1e-12);
+        assert!(instance.intersect(&Ray::new(Vec3::new(5.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).time(1.0), 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::Arc;
+use glitz::vec::Vec3;
+use glitz::aabb::Aabb;
+use crate::ray::Ray;
+use crate::material::Material;
+use crate::hittable::{Hit, Hittable};
+
+/// Indexed triangle mesh, holding the vertex buffers shared by all of its triangles.
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    indices: Vec<[u32; 3]>,
+    mat: Material,
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Vec3>, indices: Vec<[u32; 3]>, mat: Material) -> Self {
+        assert!(
+            indices.iter().flatten().all(|&i| (i as usize) < positions.len()),
+            "triangle index out of bounds of the position buffer"
+        );
+        TriangleMesh {
+            positions,
+            normals: None,
+            uvs: None,
+            indices,
+            mat,
+        }
+    }
+
+    /// Per-vertex shading normals, interpolated across each face.
+    pub fn normals(self, normals: Vec<Vec3>) -> Self {
+        assert_eq!(normals.len(), self.positions.len(), "need exactly one normal per vertex");
+        TriangleMesh { normals: Some(normals), ..self }
+    }
+
+    /// Per-vertex texture coordinates, interpolated across each face.
+    pub fn uvs(self, uvs: Vec<(f64, f64)>) -> Self {
+        assert_eq!(uvs.len(), self.positions.len(), "need exactly one uv per vertex");
+        TriangleMesh { uvs: Some(uvs), ..self }
+    }
+
+    pub fn num_triangles(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.mat
+    }
+
+    /// Splits the mesh into individual triangles that all reference the same buffers.
+    pub fn into_triangles(self) -> Vec<Triangle> {
+        let mesh = Arc::new(self);
+        (0..mesh.indices.len()).map(|index| Triangle { mesh: mesh.clone(), index }).collect()
+    }
+}
+
+/// A single face of a `TriangleMesh`.
+pub struct Triangle {
+    mesh: Arc<TriangleMesh>,
+    index: usize,
+}
+
+impl Triangle {
+    /// A lone triangle, backed by a mesh of its own.
+    pub fn new(p0: Vec3, p1: Vec3, p2: Vec3, mat: Material) -> Triangle {
+        let mesh = Arc::new(TriangleMesh::new(vec![p0, p1, p2], vec![[0, 1, 2]], mat));
+        Triangle { mesh, index: 0 }
+    }
+
+    pub fn mesh(&self) -> &Arc<TriangleMesh> {
+        &self.mesh
+    }
+
+    #[inline]
+    fn vertex_indices(&self) -> [usize; 3] {
+        let [i0, i1, i2] = self.mesh.indices[self.index];
+        [i0 as usize, i1 as usize, i2 as usize]
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> [Vec3; 3] {
+        let [i0, i1, i2] = self.vertex_indices();
+        [self.mesh.positions[i0], self.mesh.positions[i1], self.mesh.positions[i2]]
+    }
+
+    // Builds the hit at barycentric coordinates `b`, with normals facing the side given by `front_face`.
+    fn hit_at(&self, b: [f64; 3], t: f64, front_face: bool) -> Hit<'_> {
+        let [p0, p1, p2] = self.vertices();
+        let [b0, b1, b2] = b;
+
+        let point = b0 * p0 + b1 * p1 + b2 * p2;
+        let outward_normal = (p1 - p0).cross(&(p2 - p0)).unit_vec();
+        let geometric_normal = if front_face { outward_normal } else { -outward_normal };
+
+        let [i0, i1, i2] = self.vertex_indices();
+        let normal = match &self.mesh.normals {
+            Some(n) => {
+                let ns = (b0 * n[i0] + b1 * n[i1] + b2 * n[i2]).unit_vec();
+                // Keep the shading normal on the side of the surface the ray arrived from.
+                if ns.dot(&geometric_normal) < 0.0 { -ns } else { ns }
+            }
+            None => geometric_normal,
+        };
+        let [uv0, uv1, uv2] = match &self.mesh.uvs {
+            Some(uv) => [uv[i0], uv[i1], uv[i2]],
+            None => [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        };
+        let uv = (
+            b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+            b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+        );
+
+        // Texture space is flat over the triangle, so the tangents are constant across it.
+        let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+        let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let (dp02, dp12) = (p0 - p2, p1 - p2);
+        let det = du02 * dv12 - dv02 * du12;
+        let (dpdu, dpdv) = if det.abs() < 1e-12 {
+            (Vec3::default(), Vec3::default())
+        } else {
+            ((dv12 * dp02 - dv02 * dp12) / det, (du02 * dp12 - du12 * dp02) / det)
+        };
+
+        Hit::new(point, normal, t, front_face, uv, &self.mesh.mat).tangents(dpdu, dpdv).light(true)
+    }
+}
+
+impl Hittable for Triangle {
+    // Watertight ray-triangle test from Woop, Benthin and Wald 2013: transform the triangle
+    // into a space where the ray runs along +z from the origin, so that adjacent triangles
+    // evaluate their shared edge identically and rays can't slip through the crack.
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let [p0, p1, p2] = self.vertices();
+
+        let kz = {
+            let a = Vec3::new(r.d.x.abs(), r.d.y.abs(), r.d.z.abs());
+            if a.x > a.y && a.x > a.z { 0 } else if a.y > a.z { 1 } else { 2 }
+        };
+        let kx = (kz + 1) % 3;
+        let ky = (kx + 1) % 3;
+        let permute = |v: Vec3| Vec3::new(v[kx], v[ky], v[kz]);
+
+        let d = permute(r.d);
+        let sx = -d.x / d.z;
+        let sy = -d.y / d.z;
+        let sz = 1.0 / d.z;
+
+        let shear = |p: Vec3| {
+            let p = permute(p - r.o);
+            Vec3::new(p.x + sx * p.z, p.y + sy * p.z, p.z * sz)
+        };
+        let p0t = shear(p0);
+        let p1t = shear(p1);
+        let p2t = shear(p2);
+
+        let e0 = p1t.x * p2t.y - p1t.y * p2t.x;
+        let e1 = p2t.x * p0t.y - p2t.y * p0t.x;
+        let e2 = p0t.x * p1t.y - p0t.y * p1t.x;
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return None;
+        }
+        let det = e0 + e1 + e2;
+        if det == 0.0 {
+            return None;
+        }
+
+        let t = (e0 * p0t.z + e1 * p1t.z + e2 * p2t.z) / det;
+        if t < tmin || tmax < t {
+            return None;
+        }
+        let (b0, b1, b2) = (e0 / det, e1 / det, e2 / det);
+
+        let outward_normal = (p1 - p0).cross(&(p2 - p0));
+        let front_face = r.d.dot(&outward_normal) < 0.0;
+        Some(self.hit_at([b0, b1, b2], t, front_face))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let [p0, p1, p2] = self.vertices();
+        Aabb::new(p0, p1).union_point(&p2)
+    }
+
+    fn area(&self) -> f64 {
+        let [p0, p1, p2] = self.vertices();
+        0.5 * (p1 - p0).cross(&(p2 - p0)).length()
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        // Folds the unit square onto the triangle with uniform density.
+        let su0 = u.0.sqrt();
+        let b0 = 1.0 - su0;
+        let b1 = u.1 * su0;
+        Some(self.hit_at([b0, b1, 1.0 - b0 - b1], 0.0, true))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mesh.mat.is_emissive() {
+            lights.push(self);
+        }
+    }
+
+    fn object_key(&self) -> usize {
+        Arc::as_ptr(&self.mesh) as usize
+    }
+}
+
+#[cfg(test)]
+mod mesh_tests {
+    use super::*;
+    use crate::ray::RayDifferential;
+    use xenon::color::Color;
+
+    fn quad() -> Vec<Triangle> {
+        let positions = vec![
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ];
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        TriangleMesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5))))
+            .normals(vec![normal; 4])
+            .uvs(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+            .into_triangles()
+    }
+
+    #[test]
+    fn test_shared_edge_is_watertight() {
+        let tris = quad();
+        for i in 0..=100 {
+            let s = -1.0 + 2.0 * i as f64 / 100.0;
+            let r = Ray::new(Vec3::new(s, s, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            assert!(tris.iter().any(|t| t.intersect(&r, 0.0, f64::INFINITY).is_some()));
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let tris = quad();
+        let r = Ray::new(Vec3::new(0.5, -0.5, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = tris[0].intersect(&r, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-12);
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert!((hit.uv.0 - 0.75).abs() < 1e-12 && (hit.uv.1 - 0.25).abs() < 1e-12);
+        assert!(tris[1].intersect(&r, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_footprint() {
+        let tris = quad();
+        let o = Vec3::new(0.5, -0.5, 1.0);
+        let d = Vec3::new(0.0, 0.0, -1.0);
+        let r = Ray::new(o, d).differential(Some(RayDifferential {
+            rx_o: o,
+            rx_d: d + Vec3::new(0.1, 0.0, 0.0),
+            ry_o: o,
+            ry_d: d + Vec3::new(0.0, 0.1, 0.0),
+        }));
+        let mut hit = tris[0].intersect(&r, 0.0, f64::INFINITY).unwrap();
+        assert_eq!((hit.dpdu, hit.dpdv), (Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0)));
+        hit.compute_footprint(&r);
+        let fp = hit.footprint;
+        assert!((fp.dpdx - Vec3::new(0.1, 0.0, 0.0)).length() < 1e-12);
+        assert!((fp.duvdx.0 - 0.05).abs() < 1e-12 && fp.duvdx.1.abs() < 1e-12);
+        assert!(fp.duvdy.0.abs() < 1e-12 && (fp.duvdy.1 - 0.05).abs() < 1e-12);
+    }
+}
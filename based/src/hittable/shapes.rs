@@ -0,0 +1,777 @@
+use std::f64::consts::PI;
+use glitz::aabb::Aabb;
+use glitz::frame::Frame;
+use glitz::vec::Vec3;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+
+// Angle of `(x, z)` around the y axis, in [0, 2pi).
+fn azimuth(x: f64, z: f64) -> f64 {
+    let phi = z.atan2(x);
+    if phi < 0.0 { phi + 2.0 * PI } else { phi }
+}
+
+// Nearest of the two roots of `a t^2 + b t + c` in `[tmin, tmax]` for which `accept` holds.
+fn nearest_quadratic_root(a: f64, b: f64, c: f64, tmin: f64, tmax: f64, accept: impl Fn(f64) -> bool) -> Option<f64> {
+    let roots = if a.abs() < 1e-12 {
+        if b == 0.0 {
+            return None;
+        }
+        [-c / b, f64::NAN]
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        // Avoids cancellation between b and the square root.
+        let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+        let (t0, t1) = (q / a, c / q);
+        if t0 < t1 { [t0, t1] } else { [t1, t0] }
+    };
+    roots.iter().copied().find(|&t| tmin <= t && t <= tmax && accept(t))
+}
+
+/// An infinite plane through `point`. Its texture coordinates are distances along two
+/// directions in the plane, so textures repeat across it. Having no finite area, it isn't
+/// sampled as a light, so an emissive plane only lights the scene through rays that happen
+/// to hit it.
+pub struct Plane {
+    point: Vec3,
+    frame: Frame,
+    mat: Material,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, mat: Material) -> Plane {
+        Plane { point, frame: Frame::from_normal(normal.unit_vec()), mat }
+    }
+}
+
+impl Hittable for Plane {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let n = self.frame.n;
+        let denom = n.dot(&r.d);
+        if denom == 0.0 {
+            return None;
+        }
+        let t = (self.point - r.o).dot(&n) / denom;
+        if t < tmin || tmax < t {
+            return None;
+        }
+        let point = r.at(t);
+        let local = self.frame.to_local(&(point - self.point));
+        let front_face = denom < 0.0;
+        let normal = if front_face { n } else { -n };
+        Some(Hit::new(point, normal, t, front_face, (local.x, local.y), &self.mat).tangents(self.frame.t, self.frame.b))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // Flat along an axis the plane is perpendicular to, and unbounded otherwise.
+        let n = self.frame.n;
+        let mut min = [f64::NEG_INFINITY; 3];
+        let mut max = [f64::INFINITY; 3];
+        for axis in 0..3 {
+            if n[axis].abs() == 1.0 {
+                min[axis] = self.point[axis];
+                max[axis] = self.point[axis];
+            }
+        }
+        Aabb::new(Vec3::from(min), Vec3::from(max))
+    }
+}
+
+/// The parallelogram with a corner at `q` and sides `u` and `v`, such as a rectangle. Its
+/// front is the side `u.cross(v)` points to, and texture coordinates run from 0 to 1 along
+/// each side.
+pub struct Quad {
+    q: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    // Turns offsets in the plane into coordinates along the sides.
+    w: Vec3,
+    mat: Material,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, mat: Material) -> Quad {
+        let n = u.cross(&v);
+        Quad { q, u, v, normal: n.unit_vec(), w: n / n.dot(&n), mat }
+    }
+
+    fn hit_at(&self, uv: (f64, f64), t: f64, front_face: bool) -> Hit<'_> {
+        let point = self.q + uv.0 * self.u + uv.1 * self.v;
+        let normal = if front_face { self.normal } else { -self.normal };
+        Hit::new(point, normal, t, front_face, uv, &self.mat).tangents(self.u, self.v).light(true)
+    }
+}
+
+impl Hittable for Quad {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let denom = self.normal.dot(&r.d);
+        if denom == 0.0 {
+            return None;
+        }
+        let t = (self.q - r.o).dot(&self.normal) / denom;
+        if t < tmin || tmax < t {
+            return None;
+        }
+        let h = r.at(t) - self.q;
+        let alpha = self.w.dot(&h.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&h));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        Some(self.hit_at((alpha, beta), t, denom < 0.0))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.q, self.q + self.u + self.v)
+            .union_point(&(self.q + self.u))
+            .union_point(&(self.q + self.v))
+    }
+
+    fn area(&self) -> f64 {
+        self.u.cross(&self.v).length()
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        Some(self.hit_at(u, 0.0, true))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mat.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// A flat disk facing along `normal`. Texture coordinates are the angle around it as a
+/// fraction of a turn and the distance from the center as a fraction of the radius.
+pub struct Disk {
+    center: Vec3,
+    frame: Frame,
+    radius: f64,
+    mat: Material,
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, mat: Material) -> Disk {
+        Disk { center, frame: Frame::from_normal(normal.unit_vec()), radius, mat }
+    }
+
+    // Builds the hit at `(x, y)` in the disk's own frame.
+    fn hit_at(&self, x: f64, y: f64, t: f64, front_face: bool) -> Hit<'_> {
+        let Frame { t: tx, b: ty, n } = self.frame;
+        let rho = (x * x + y * y).sqrt();
+        let uv = (azimuth(x, y) / (2.0 * PI), rho / self.radius);
+        let dpdu = 2.0 * PI * (-y * tx + x * ty);
+        let dpdv = if rho > 0.0 { self.radius / rho * (x * tx + y * ty) } else { Vec3::default() };
+        let normal = if front_face { n } else { -n };
+        Hit::new(self.center + x * tx + y * ty, normal, t, front_face, uv, &self.mat).tangents(dpdu, dpdv).light(true)
+    }
+}
+
+impl Hittable for Disk {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let n = self.frame.n;
+        let denom = n.dot(&r.d);
+        if denom == 0.0 {
+            return None;
+        }
+        let t = (self.center - r.o).dot(&n) / denom;
+        if t < tmin || tmax < t {
+            return None;
+        }
+        let local = self.frame.to_local(&(r.at(t) - self.center));
+        if local.x * local.x + local.y * local.y > self.radius * self.radius {
+            return None;
+        }
+        Some(self.hit_at(local.x, local.y, t, denom < 0.0))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // Along each axis the rim reaches as far as the radius times the sine of the angle
+        // between the axis and the normal.
+        let n = self.frame.n;
+        let extent = |a: f64| self.radius * (1.0 - a * a).max(0.0).sqrt();
+        let extent = Vec3::new(extent(n.x), extent(n.y), extent(n.z));
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        let rho = self.radius * u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        Some(self.hit_at(rho * phi.cos(), rho * phi.sin(), 0.0, true))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mat.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// An axis-aligned box between the corners `min` and `max`. Texture coordinates run from 0
+/// to 1 across each face.
+pub struct Cuboid {
+    bounds: Aabb,
+    mat: Material,
+}
+
+impl Cuboid {
+    pub fn new(min: Vec3, max: Vec3, mat: Material) -> Cuboid {
+        Cuboid { bounds: Aabb::new(min, max), mat }
+    }
+
+    // Builds the hit on the face perpendicular to `axis` on the side given by `sign`.
+    fn hit_at(&self, point: Vec3, axis: usize, sign: f64, t: f64, front_face: bool) -> Hit<'_> {
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let size = self.bounds.diagonal();
+        let offset = self.bounds.offset(&point);
+        let mut outward = [0.0; 3];
+        outward[axis] = sign;
+        let outward = Vec3::from(outward);
+        let normal = if front_face { outward } else { -outward };
+        let (mut dpdu, mut dpdv) = ([0.0; 3], [0.0; 3]);
+        dpdu[a] = size[a];
+        dpdv[b] = size[b];
+        Hit::new(point, normal, t, front_face, (offset[a], offset[b]), &self.mat).tangents(Vec3::from(dpdu), Vec3::from(dpdv)).light(true)
+    }
+}
+
+impl Hittable for Cuboid {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        // The slab test, remembering which face the ray enters and leaves through.
+        let (mut t0, mut t1) = (f64::NEG_INFINITY, f64::INFINITY);
+        let (mut axis0, mut axis1) = (0, 0);
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.d[axis];
+            let mut near = (self.bounds.min[axis] - r.o[axis]) * inv_d;
+            let mut far = (self.bounds.max[axis] - r.o[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            if near > t0 {
+                t0 = near;
+                axis0 = axis;
+            }
+            if far < t1 {
+                t1 = far;
+                axis1 = axis;
+            }
+        }
+        if t1 < t0 {
+            return None;
+        }
+        // Entering, the outward normal faces against the ray, and leaving, along it.
+        let (t, axis, sign) = if tmin <= t0 && t0 <= tmax {
+            (t0, axis0, -r.d[axis0].signum())
+        } else if tmin <= t1 && t1 <= tmax {
+            (t1, axis1, r.d[axis1].signum())
+        } else {
+            return None;
+        };
+        let mut point = r.at(t);
+        // Snaps the point onto the face, which rounding may have moved it off.
+        let mut coords = [point.x, point.y, point.z];
+        coords[axis] = if sign > 0.0 { self.bounds.max[axis] } else { self.bounds.min[axis] };
+        point = Vec3::from(coords);
+        Some(self.hit_at(point, axis, sign, t, t == t0))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn area(&self) -> f64 {
+        self.bounds.surface_area()
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        // Picks one of the six faces in proportion to its area, reusing what's left of `u.0`.
+        let size = self.bounds.diagonal();
+        let face_areas = [size.y * size.z, size.z * size.x, size.x * size.y];
+        let total = 2.0 * (face_areas[0] + face_areas[1] + face_areas[2]);
+        let mut target = u.0 * total;
+        for (face, &area) in face_areas.iter().chain(face_areas.iter()).enumerate() {
+            if target < area || face == 5 {
+                let (axis, sign) = (face % 3, if face < 3 { -1.0 } else { 1.0 });
+                let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+                let mut p = [0.0; 3];
+                p[axis] = if sign > 0.0 { self.bounds.max[axis] } else { self.bounds.min[axis] };
+                p[a] = self.bounds.min[a] + (target / area).min(1.0) * size[a];
+                p[b] = self.bounds.min[b] + u.1 * size[b];
+                return Some(self.hit_at(Vec3::from(p), axis, sign, 0.0, true));
+            }
+            target -= area;
+        }
+        None
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mat.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// A tube standing upright on the circle of `radius` around `base`, `height` tall and open
+/// at both ends; `Disk`s can close it off. Texture coordinates are the angle around it as a
+/// fraction of a turn and the height as a fraction of the whole. Place it with an
+/// `Instance` to have it point another way.
+pub struct Cylinder {
+    base: Vec3,
+    radius: f64,
+    height: f64,
+    mat: Material,
+}
+
+impl Cylinder {
+    pub fn new(base: Vec3, radius: f64, height: f64, mat: Material) -> Cylinder {
+        Cylinder { base, radius, height, mat }
+    }
+
+    // Builds the hit at `p`, relative to the base.
+    fn hit_at(&self, p: Vec3, t: f64, front_face: bool) -> Hit<'_> {
+        let outward = Vec3::new(p.x, 0.0, p.z) / self.radius;
+        let normal = if front_face { outward } else { -outward };
+        let uv = (azimuth(p.x, p.z) / (2.0 * PI), p.y / self.height);
+        let dpdu = 2.0 * PI * Vec3::new(-p.z, 0.0, p.x);
+        let dpdv = Vec3::new(0.0, self.height, 0.0);
+        Hit::new(self.base + p, normal, t, front_face, uv, &self.mat).tangents(dpdu, dpdv).light(true)
+    }
+}
+
+impl Hittable for Cylinder {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let o = r.o - self.base;
+        let d = r.d;
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+        let within_height = |t: f64| (0.0..=self.height).contains(&(o.y + t * d.y));
+        let t = nearest_quadratic_root(a, b, c, tmin, tmax, within_height)?;
+
+        let p = o + t * d;
+        let front_face = d.dot(&Vec3::new(p.x, 0.0, p.z)) < 0.0;
+        Some(self.hit_at(p, t, front_face))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = self.radius;
+        Aabb::new(self.base - Vec3::new(r, 0.0, r), self.base + Vec3::new(r, self.height, r))
+    }
+
+    fn area(&self) -> f64 {
+        2.0 * PI * self.radius * self.height
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        let phi = 2.0 * PI * u.0;
+        let p = Vec3::new(self.radius * phi.cos(), self.height * u.1, self.radius * phi.sin());
+        Some(self.hit_at(p, 0.0, true))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mat.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// A cone standing upright on the circle of `radius` around `base`, with its tip `height`
+/// above, open at the bottom. Texture coordinates are as for `Cylinder`.
+pub struct Cone {
+    base: Vec3,
+    radius: f64,
+    height: f64,
+    mat: Material,
+}
+
+impl Cone {
+    pub fn new(base: Vec3, radius: f64, height: f64, mat: Material) -> Cone {
+        Cone { base, radius, height, mat }
+    }
+
+    // Builds the hit at angle `phi` around and fraction `v` of the way up.
+    fn hit_at(&self, phi: f64, v: f64, t: f64, front_face: bool) -> Hit<'_> {
+        let (r, h) = (self.radius, self.height);
+        let (sin, cos) = phi.sin_cos();
+        let p = Vec3::new((1.0 - v) * r * cos, v * h, (1.0 - v) * r * sin);
+        // Slanted away from the axis by the same angle the side leans in by.
+        let outward = Vec3::new(h * cos, r, h * sin).unit_vec();
+        let normal = if front_face { outward } else { -outward };
+        let dpdu = 2.0 * PI * Vec3::new(-p.z, 0.0, p.x);
+        let dpdv = Vec3::new(-r * cos, h, -r * sin);
+        Hit::new(self.base + p, normal, t, front_face, (phi / (2.0 * PI), v), &self.mat).tangents(dpdu, dpdv).light(true)
+    }
+}
+
+impl Hittable for Cone {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        // Points where x^2 + z^2 = (k (h - y))^2, with k the radius shrinking per unit height.
+        let o = r.o - self.base;
+        let d = r.d;
+        let (h, k2) = (self.height, (self.radius / self.height).powi(2));
+        let a = d.x * d.x + d.z * d.z - k2 * d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.z * d.z + k2 * (h - o.y) * d.y);
+        let c = o.x * o.x + o.z * o.z - k2 * (h - o.y) * (h - o.y);
+        let within_height = |t: f64| (0.0..=h).contains(&(o.y + t * d.y));
+        let t = nearest_quadratic_root(a, b, c, tmin, tmax, within_height)?;
+
+        let p = o + t * d;
+        let phi = azimuth(p.x, p.z);
+        let v = p.y / h;
+        let outward = Vec3::new(h * phi.cos(), self.radius, h * phi.sin());
+        let front_face = d.dot(&outward) < 0.0;
+        let mut hit = self.hit_at(phi, v, t, front_face);
+        hit.point = self.base + p;
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = self.radius;
+        Aabb::new(self.base - Vec3::new(r, 0.0, r), self.base + Vec3::new(r, self.height, r))
+    }
+
+    fn area(&self) -> f64 {
+        PI * self.radius * (self.radius * self.radius + self.height * self.height).sqrt()
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        // The circumference shrinks linearly towards the tip, and the density with it.
+        Some(self.hit_at(2.0 * PI * u.1, 1.0 - u.0.sqrt(), 0.0, true))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mat.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+/// A ring around `center` in the xz plane, made by sweeping a circle of radius `minor` around
+/// one of radius `major`. Texture coordinates are the angles around the ring and around the
+/// tube, each as a fraction of a turn. The ring must be wider than the tube.
+pub struct Torus {
+    center: Vec3,
+    major: f64,
+    minor: f64,
+    mat: Material,
+}
+
+impl Torus {
+    pub fn new(center: Vec3, major: f64, minor: f64, mat: Material) -> Torus {
+        Torus { center, major, minor, mat }
+    }
+
+    // Builds the hit at angle `phi` around the ring and `theta` around the tube.
+    fn hit_at(&self, phi: f64, theta: f64, t: f64, front_face: bool) -> Hit<'_> {
+        let (big, small) = (self.major, self.minor);
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let outward = Vec3::new(cos_theta * cos_phi, sin_theta, cos_theta * sin_phi);
+        let p = Vec3::new(big * cos_phi, 0.0, big * sin_phi) + small * outward;
+        let normal = if front_face { outward } else { -outward };
+        let dpdu = 2.0 * PI * Vec3::new(-p.z, 0.0, p.x);
+        let dpdv = 2.0 * PI * small * Vec3::new(-sin_theta * cos_phi, cos_theta, -sin_theta * sin_phi);
+        let uv = (phi / (2.0 * PI), theta / (2.0 * PI));
+        Hit::new(self.center + p, normal, t, front_face, uv, &self.mat).tangents(dpdu, dpdv).light(true)
+    }
+
+    // Angle around the tube for `u` uniform in [0, 1), more likely on the outside, which is
+    // further from the axis and so has more area. Inverts the distribution with Newton's
+    // method, falling back to bisection whenever a step leaves the bracket.
+    fn sample_theta(&self, u: f64) -> f64 {
+        let (big, small) = (self.major, self.minor);
+        let target = 2.0 * PI * big * u;
+        let (mut lo, mut hi) = (0.0, 2.0 * PI);
+        let mut theta = 2.0 * PI * u;
+        for _ in 0..64 {
+            let f = big * theta + small * theta.sin() - target;
+            if f.abs() < 1e-12 * big {
+                break;
+            }
+            if f < 0.0 { lo = theta } else { hi = theta }
+            let next = theta - f / (big + small * theta.cos());
+            theta = if lo < next && next < hi { next } else { 0.5 * (lo + hi) };
+        }
+        theta
+    }
+}
+
+impl Hittable for Torus {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let (big, small) = (self.major, self.minor);
+        let length = r.d.length();
+        let d = r.d / length;
+        // Starting the quartic where the ray enters the bounds keeps its coefficients small.
+        let local = Ray::new(r.o - self.center, d);
+        let bounds = Aabb::new(-Vec3::new(big + small, small, big + small), Vec3::new(big + small, small, big + small));
+        let (start, end) = clip(&bounds, &local, tmin * length, tmax * length)?;
+        let o = local.at(start);
+
+        // |p|^2 + R^2 - r^2 squared equals 4 R^2 (x^2 + z^2) on the surface.
+        let e = o.dot(&o) + big * big - small * small;
+        let f = o.dot(&d);
+        let four_r2 = 4.0 * big * big;
+        let coeffs = [
+            e * e - four_r2 * (o.x * o.x + o.z * o.z),
+            4.0 * e * f - 2.0 * four_r2 * (o.x * d.x + o.z * d.z),
+            4.0 * f * f + 2.0 * e - four_r2 * (d.x * d.x + d.z * d.z),
+            4.0 * f,
+            1.0,
+        ];
+        let s = first_root(&coeffs, 0.0, end - start)?;
+        let t = (start + s) / length;
+
+        let p = o + s * d;
+        let phi = azimuth(p.x, p.z);
+        let theta = {
+            let theta = p.y.atan2((p.x * p.x + p.z * p.z).sqrt() - big);
+            if theta < 0.0 { theta + 2.0 * PI } else { theta }
+        };
+        let outward = Vec3::new(theta.cos() * phi.cos(), theta.sin(), theta.cos() * phi.sin());
+        let mut hit = self.hit_at(phi, theta, t, d.dot(&outward) < 0.0);
+        hit.point = self.center + p;
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3::new(self.major + self.minor, self.minor, self.major + self.minor);
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * PI * self.major * self.minor
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        Some(self.hit_at(2.0 * PI * u.1, self.sample_theta(u.0), 0.0, true))
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        if self.mat.is_emissive() {
+            lights.push(self);
+        }
+    }
+}
+
+// Part of `r` in `[tmin, tmax]` inside `bounds`, if any.
+fn clip(bounds: &Aabb, r: &Ray, tmin: f64, tmax: f64) -> Option<(f64, f64)> {
+    let (mut t0, mut t1) = (tmin, tmax);
+    for axis in 0..3 {
+        let inv_d = 1.0 / r.d[axis];
+        let mut near = (bounds.min[axis] - r.o[axis]) * inv_d;
+        let mut far = (bounds.max[axis] - r.o[axis]) * inv_d;
+        if inv_d < 0.0 {
+            std::mem::swap(&mut near, &mut far);
+        }
+        t0 = if near > t0 { near } else { t0 };
+        t1 = if far < t1 { far } else { t1 };
+        if t1 < t0 {
+            return None;
+        }
+    }
+    Some((t0, t1))
+}
+
+// Value at `x` of the polynomial with coefficients `coeffs`, constant term first.
+fn polynomial(coeffs: &[f64], x: f64) -> f64 {
+    coeffs.iter().rev().fold(0.0, |sum, &c| sum * x + c)
+}
+
+// Smallest root in `(lo, hi]` of the polynomial with coefficients `coeffs`, constant term
+// first. Between the roots of its derivative the polynomial is monotonic, so each interval
+// they split `[lo, hi]` into holds at most one root, which bisection finds reliably.
+fn first_root(coeffs: &[f64], lo: f64, hi: f64) -> Option<f64> {
+    roots(coeffs, lo, hi).into_iter().next()
+}
+
+fn roots(coeffs: &[f64], lo: f64, hi: f64) -> Vec<f64> {
+    if coeffs.len() <= 2 {
+        let root = -coeffs[0] / coeffs.get(1).copied().unwrap_or(0.0);
+        return if lo < root && root <= hi { vec![root] } else { vec![] };
+    }
+    let derivative = coeffs[1..].iter().enumerate().map(|(i, &c)| (i + 1) as f64 * c).collect::<Vec<_>>();
+    let mut ends = vec![lo];
+    ends.extend(roots(&derivative, lo, hi));
+    ends.push(hi);
+
+    ends.windows(2).filter_map(|w| {
+        let (mut a, mut b) = (w[0], w[1]);
+        let (fa, fb) = (polynomial(coeffs, a), polynomial(coeffs, b));
+        if fb == 0.0 {
+            return Some(b);
+        }
+        if fa.signum() == fb.signum() {
+            return None;
+        }
+        for _ in 0..64 {
+            let mid = 0.5 * (a + b);
+            if mid <= a || mid >= b {
+                break;
+            }
+            if polynomial(coeffs, mid).signum() == fa.signum() { a = mid } else { b = mid }
+        }
+        Some(b)
+    }).collect()
+}
+
+#[cfg(test)]
+mod shapes_tests {
+    use super::*;
+    use std::sync::Arc;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+    use xenon::color::Color;
+    use crate::hittable::bvh::Bvh;
+    use crate::hittable::Sphere;
+    use crate::sampling::uniform_sphere;
+
+    fn grey() -> Material {
+        Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    /// Fires rays from all around at `shape`, checking each hit lies where `surface` is zero,
+    /// inside the bounds, with a unit normal facing the ray, tangents perpendicular to it
+    /// and texture coordinates in the unit square.
+    fn check_hits(shape: &dyn Hittable, surface: impl Fn(Vec3) -> f64, target: Vec3) -> usize {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(9);
+        let bounds = shape.bounding_box();
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let o = target + 6.0 * uniform_sphere(rng.gen());
+            let d = (target + 1.5 * uniform_sphere(rng.gen()) - o) * 0.5;
+            let r = Ray::new(o, d);
+            let hit = match shape.intersect(&r, 0.0, f64::INFINITY) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            hits += 1;
+            let p = hit.point;
+            assert!((p - r.at(hit.t)).length() < 1e-6, "{:?} {:?}", p, r.at(hit.t));
+            assert!(surface(p).abs() < 1e-6, "{:?} {}", p, surface(p));
+            assert!((0..3).all(|i| bounds.min[i] - 1e-9 <= p[i] && p[i] <= bounds.max[i] + 1e-9));
+            assert!((hit.normal.length() - 1.0).abs() < 1e-9 && hit.normal.dot(&r.d) <= 0.0);
+            assert!(hit.dpdu.dot(&hit.normal).abs() < 1e-6 && hit.dpdv.dot(&hit.normal).abs() < 1e-6);
+            assert!((0.0..=1.0).contains(&hit.uv.0) && (0.0..=1.0).contains(&hit.uv.1), "{:?}", hit.uv);
+            // Nothing closer was missed.
+            assert!(shape.intersect(&r, 0.0, hit.t * (1.0 - 1e-6)).is_none());
+        }
+        hits
+    }
+
+    /// Checks `sample_surface` lands on the surface and has a normal agreeing with the one
+    /// a ray arriving from outside would see.
+    fn check_samples(shape: &dyn Hittable, surface: impl Fn(Vec3) -> f64) {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(10);
+        for _ in 0..200 {
+            let sample = shape.sample_surface(rng.gen()).unwrap();
+            assert!(surface(sample.point).abs() < 1e-6);
+            let r = Ray::new(sample.point + sample.normal, -sample.normal);
+            let hit = shape.intersect(&r, 0.0, f64::INFINITY).unwrap();
+            assert!(hit.front_face && (hit.point - sample.point).length() < 1e-6 && (hit.normal - sample.normal).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_flat() {
+        let quad = Quad::new(Vec3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.5, 0.0, -3.0), grey());
+        let hits = check_hits(&quad, |p| p.y, Vec3::new(2.0, 0.0, -1.0));
+        assert!(hits > 100);
+        check_samples(&quad, |p| p.y);
+        assert_eq!(quad.area(), 6.0);
+        let hit = quad.intersect(&Ray::new(Vec3::new(2.25, 1.0, -1.5), Vec3::new(0.0, -1.0, 0.0)), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.uv.0 - 0.5).abs() < 1e-12 && (hit.uv.1 - 0.5).abs() < 1e-12 && hit.front_face);
+
+        let normal = Vec3::new(1.0, 2.0, 2.0) / 3.0;
+        let center = Vec3::new(0.0, 1.0, 0.0);
+        let disk = Disk::new(center, normal, 1.5, grey());
+        let on_disk = |p: Vec3| (p - center).dot(&normal);
+        assert!(check_hits(&disk, on_disk, center) > 100);
+        check_samples(&disk, on_disk);
+
+        let plane = Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), grey());
+        let bounds = plane.bounding_box();
+        assert!(bounds.min.y == -1.0 && bounds.max.y == -1.0 && bounds.min.x == f64::NEG_INFINITY);
+        let hit = plane.intersect(&Ray::new(Vec3::new(3.0, 2.0, 5.0), Vec3::new(0.0, -1.0, 0.0)), 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.t, 3.0);
+    }
+
+    #[test]
+    fn test_cuboid() {
+        let (min, max) = (Vec3::new(-1.0, 0.0, -2.0), Vec3::new(1.0, 1.0, 0.5));
+        let cuboid = Cuboid::new(min, max, grey());
+        let on_box = |p: Vec3| {
+            // Zero on the surface: inside along two axes and on a face along the third.
+            let d = (0..3).map(|i| (p[i] - min[i]).min(max[i] - p[i])).fold(f64::INFINITY, f64::min);
+            d.abs()
+        };
+        assert!(check_hits(&cuboid, on_box, Vec3::new(0.0, 0.5, -0.75)) > 100);
+        check_samples(&cuboid, on_box);
+
+        // From inside, the far face is hit from behind.
+        let hit = cuboid.intersect(&Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0)), 0.0, f64::INFINITY).unwrap();
+        assert!(hit.t == 1.0 && !hit.front_face && hit.normal == Vec3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_round() {
+        let base = Vec3::new(1.0, -1.0, 0.0);
+        let cylinder = Cylinder::new(base, 0.8, 2.0, grey());
+        let on_cylinder = |p: Vec3| ((p.x - base.x).powi(2) + (p.z - base.z).powi(2)).sqrt() - 0.8;
+        assert!(check_hits(&cylinder, on_cylinder, base + Vec3::new(0.0, 1.0, 0.0)) > 100);
+        check_samples(&cylinder, on_cylinder);
+
+        let cone = Cone::new(base, 1.0, 2.0, grey());
+        let on_cone = |p: Vec3| ((p.x - base.x).powi(2) + (p.z - base.z).powi(2)).sqrt() - (1.0 - (p.y - base.y) / 2.0);
+        assert!(check_hits(&cone, on_cone, base + Vec3::new(0.0, 1.0, 0.0)) > 100);
+        check_samples(&cone, on_cone);
+        assert!((cone.area() - PI * 5.0f64.sqrt()).abs() < 1e-12);
+
+        let torus = Torus::new(base, 1.5, 0.5, grey());
+        let on_torus = |p: Vec3| {
+            let p = p - base;
+            (((p.x * p.x + p.z * p.z).sqrt() - 1.5).powi(2) + p.y * p.y).sqrt() - 0.5
+        };
+        assert!(check_hits(&torus, on_torus, base) > 100);
+        check_samples(&torus, on_torus);
+        assert!((torus.area() - 3.0 * PI * PI).abs() < 1e-12);
+        // Area, and so samples, lean to the outside, making the mean cosine around the tube
+        // the ratio of its radius to twice the ring's.
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(11);
+        let mean_cos = (0..20_000).map(|_| {
+            let p = torus.sample_surface(rng.gen()).unwrap().point - base;
+            ((p.x * p.x + p.z * p.z).sqrt() - 1.5) / 0.5
+        }).sum::<f64>() / 20_000.0;
+        assert!((mean_cos - 1.0 / 6.0).abs() < 0.01, "{}", mean_cos);
+        // Straight through the hole misses, and across the ring hits the near side of it.
+        assert!(torus.intersect(&Ray::new(base + Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), 0.0, f64::INFINITY).is_none());
+        let hit = torus.intersect(&Ray::new(base + Vec3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)), 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.5).abs() < 1e-9 && (hit.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_unbounded_in_bvh() {
+        // An infinite plane among other objects doesn't upset the hierarchy.
+        let mut prims: Vec<Box<dyn Hittable>> = vec![Box::new(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), grey()))];
+        for i in 0..20 {
+            prims.push(Box::new(Sphere::new(Vec3::new(3.0 * i as f64, 1.0, 0.0), 1.0, grey())));
+        }
+        let bvh = Bvh::new(prims);
+        for i in 0..20 {
+            let x = 3.0 * i as f64;
+            let hit = bvh.intersect(&Ray::new(Vec3::new(x, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), 0.0, f64::INFINITY).unwrap();
+            assert!((hit.point.y - 2.0).abs() < 1e-9);
+            let hit = bvh.intersect(&Ray::new(Vec3::new(x + 1.5, 5.0, 100.0), Vec3::new(0.0, -1.0, 0.0)), 0.0, f64::INFINITY).unwrap();
+            assert!(hit.point.y.abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use glitz::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::medium::Medium;
+use crate::ray::Ray;
+
+/// A closed surface filled with a participating medium. Light entering through the surface
+/// travels through the medium until it leaves again, back into the scene's own medium if it
+/// has one. Give the boundary `Material::Interface` for a volume on its own, like a cloud of
+/// smoke, or a real material, like glass around a milky liquid.
+///
+/// Volumes can't overlap or nest, and the camera must start outside all of them.
+pub struct Volume {
+    boundary: Box<dyn Hittable>,
+    medium: Box<dyn Medium>,
+}
+
+impl Volume {
+    pub fn new(boundary: impl Hittable + 'static, medium: impl Medium + 'static) -> Self {
+        Volume { boundary: Box::new(boundary), medium: Box::new(medium) }
+    }
+}
+
+impl Hittable for Volume {
+    fn intersect(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<Hit<'_>> {
+        let mut hit = self.boundary.intersect(r, tmin, tmax)?;
+        hit.medium = Some(self.medium.as_ref());
+        Some(hit)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn area(&self) -> f64 {
+        self.boundary.area()
+    }
+
+    fn sample_surface(&self, u: (f64, f64)) -> Option<Hit<'_>> {
+        self.boundary.sample_surface(u)
+    }
+
+    fn collect_lights<'a>(&'a self, lights: &mut Vec<&'a dyn Hittable>) {
+        self.boundary.collect_lights(lights)
+    }
+}
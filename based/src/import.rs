@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use glitz::vec::Vec3;
+
+pub mod obj;
+pub mod ply;
+pub mod hdr;
+pub mod png;
+pub mod raw;
+
+/// Failure to read a scene asset from disk.
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    /// The input is malformed; `line` is the 1-based line it was found on, where that makes sense.
+    Parse { line: Option<usize>, message: String },
+}
+
+impl ImportError {
+    pub(crate) fn at_line(line: usize, message: impl Into<String>) -> Self {
+        ImportError::Parse { line: Some(line), message: message.into() }
+    }
+
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        ImportError::Parse { line: None, message: message.into() }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "io error: {}", e),
+            ImportError::Parse { line: Some(line), message } => write!(f, "line {}: {}", line, message),
+            ImportError::Parse { line: None, message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            ImportError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+// Ear clipping in the polygon's own plane, so concave faces triangulate correctly.
+// Degenerate polygons that stop yielding ears fall back to a fan over what remains.
+pub(crate) fn triangulate(polygon: &[Vec3]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Newell's method, which is robust to concave and slightly non-planar polygons.
+    let mut normal = Vec3::default();
+    for i in 0..n {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % n];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+
+    let inside = |p: Vec3, a: Vec3, b: Vec3, c: Vec3| {
+        (b - a).cross(&(p - a)).dot(&normal) >= 0.0
+            && (c - b).cross(&(p - b)).dot(&normal) >= 0.0
+            && (a - c).cross(&(p - c)).dot(&normal) >= 0.0
+    };
+
+    let mut remaining = (0..n).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(n - 2);
+    let mut i = 0;
+    let mut since_last_ear = 0;
+    while remaining.len() > 3 && since_last_ear < remaining.len() {
+        let m = remaining.len();
+        let (ia, ib, ic) = (remaining[(i + m - 1) % m], remaining[i % m], remaining[(i + 1) % m]);
+        let (a, b, c) = (polygon[ia], polygon[ib], polygon[ic]);
+        let convex = (b - a).cross(&(c - b)).dot(&normal) > 0.0;
+        let is_ear = convex && remaining.iter()
+            .filter(|&&j| j != ia && j != ib && j != ic)
+            .all(|&j| !inside(polygon[j], a, b, c));
+        if is_ear {
+            triangles.push([ia, ib, ic]);
+            remaining.remove(i % m);
+            since_last_ear = 0;
+        } else {
+            i += 1;
+            since_last_ear += 1;
+        }
+    }
+    for k in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[k], remaining[k + 1]]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod import_tests {
+    use super::*;
+
+    #[test]
+    fn test_triangulate_concave() {
+        let ell = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(1.0, 3.0, 0.0),
+            Vec3::new(0.0, 3.0, 0.0),
+        ];
+        let area: f64 = triangulate(&ell).iter()
+            .map(|&[a, b, c]| 0.5 * (ell[b] - ell[a]).cross(&(ell[c] - ell[a])).z)
+            .sum();
+        assert!((area - 4.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use xenon::color::Color;
+use crate::image::Image;
+use crate::import::ImportError;
+
+// Larger images are taken to be corrupt rather than attempted, since they wouldn't fit in
+// memory anyway.
+const MAX_PIXELS: usize = 1 << 28;
+
+/// Loads a Radiance RGBE (`.hdr`) image.
+pub fn load_hdr(path: impl AsRef<Path>) -> Result<Image, ImportError> {
+    let file = File::open(path)?;
+    parse_hdr(BufReader::new(file))
+}
+
+/// Parses Radiance RGBE data, either flat or run-length encoded.
+pub fn parse_hdr(mut reader: impl BufRead) -> Result<Image, ImportError> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(ImportError::at_line(1, "not a Radiance file, expected `#?RADIANCE`"));
+    }
+
+    let mut line_num = 1;
+    loop {
+        line.clear();
+        line_num += 1;
+        if reader.read_line(&mut line)? == 0 {
+            return Err(ImportError::at_line(line_num, "file ended inside the header"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(ImportError::at_line(line_num, format!("unsupported pixel format `{}`", format)));
+            }
+        }
+    }
+
+    line.clear();
+    line_num += 1;
+    reader.read_line(&mut line)?;
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let (flip, height, width) = match tokens.as_slice() {
+        [y, h, "+X", w] if *y == "-Y" || *y == "+Y" => {
+            let parse = |s: &str| s.parse::<usize>().map_err(|_| ImportError::at_line(line_num, format!("invalid size `{}`", s)));
+            (*y == "+Y", parse(h)?, parse(w)?)
+        }
+        _ => return Err(ImportError::at_line(line_num, format!("unsupported resolution line `{}`", line.trim()))),
+    };
+    if width == 0 || height == 0 {
+        return Err(ImportError::at_line(line_num, format!("image of {} by {} pixels is empty", width, height)));
+    }
+    let num_pixels = width.checked_mul(height).filter(|&n| n <= MAX_PIXELS)
+        .ok_or_else(|| ImportError::at_line(line_num, format!("image of {} by {} pixels is too large", width, height)))?;
+
+    let mut pixels = Vec::with_capacity(num_pixels);
+    let mut scanline = vec![[0u8; 4]; width];
+    for y in 0..height {
+        read_scanline(&mut reader, &mut scanline)
+            .map_err(|e| ImportError::new(format!("scanline {}: {}", y, e)))?;
+        pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_color(rgbe)));
+    }
+    if flip {
+        pixels = pixels.chunks_exact(width).rev().flatten().copied().collect();
+    }
+    Ok(Image::new(width, height, pixels))
+}
+
+fn rgbe_to_color([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        Color::new(0.0, 0.0, 0.0)
+    } else {
+        let f = 2.0f64.powi(e as i32 - (128 + 8));
+        Color::new((r as f64 + 0.5) * f, (g as f64 + 0.5) * f, (b as f64 + 0.5) * f)
+    }
+}
+
+// Reads one row, which is either flat RGBE pixels or, for widths in [8, 32767], may
+// be run-length encoded one channel at a time.
+fn read_scanline(reader: &mut impl Read, scanline: &mut [[u8; 4]]) -> Result<(), String> {
+    let width = scanline.len();
+    let mut first = [0u8; 4];
+    reader.read_exact(&mut first).map_err(|e| e.to_string())?;
+
+    if !(8..=0x7fff).contains(&width) || first[0] != 2 || first[1] != 2 || first[2] & 0x80 != 0 {
+        scanline[0] = first;
+        for pixel in scanline.iter_mut().skip(1) {
+            reader.read_exact(pixel).map_err(|e| e.to_string())?;
+        }
+        return Ok(());
+    }
+
+    let encoded_width = ((first[2] as usize) << 8) | first[3] as usize;
+    if encoded_width != width {
+        return Err(format!("encoded width {} doesn't match image width {}", encoded_width, width));
+    }
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            reader.read_exact(&mut count).map_err(|e| e.to_string())?;
+            let (run, count) = if count[0] > 128 { (true, count[0] as usize - 128) } else { (false, count[0] as usize) };
+            if count == 0 || x + count > width {
+                return Err("bad run length".to_string());
+            }
+            if run {
+                let mut value = [0u8; 1];
+                reader.read_exact(&mut value).map_err(|e| e.to_string())?;
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = value[0];
+                }
+            } else {
+                for pixel in &mut scanline[x..x + count] {
+                    let mut value = [0u8; 1];
+                    reader.read_exact(&mut value).map_err(|e| e.to_string())?;
+                    pixel[channel] = value[0];
+                }
+            }
+            x += count;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod hdr_tests {
+    use super::*;
+
+    #[test]
+    fn test_flat() {
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 1\n".to_vec();
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+        let image = parse_hdr(&data[..]).unwrap();
+        assert_eq!((image.width(), image.height()), (1, 2));
+        let c = image.get(0, 0);
+        assert!((c.r - 1.0).abs() < 0.01 && (c.g - 0.5).abs() < 0.01 && c.b < 0.01);
+        assert_eq!(image.get(0, 1).r, 0.0);
+    }
+
+    #[test]
+    fn test_bad_size() {
+        for resolution in ["-Y 2 +X 0", "+Y 0 +X 3", "-Y 4294967296 +X 4294967296", "-Y 100000 +X 100000"] {
+            let mut data = format!("#?RADIANCE\n\n{}\n", resolution).into_bytes();
+            data.extend_from_slice(&[0; 16]);
+            assert!(matches!(parse_hdr(&data[..]), Err(ImportError::Parse { line: Some(3), .. })), "{}", resolution);
+        }
+    }
+
+    #[test]
+    fn test_rle() {
+        let mut data = b"#?RADIANCE\n\n+Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // Red as one run, green and blue as literals, exponent as a run.
+        data.extend_from_slice(&[128 + 8, 128]);
+        data.extend_from_slice(&[8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        data.extend_from_slice(&[8, 0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&[128 + 8, 129]);
+        let image = parse_hdr(&data[..]).unwrap();
+        assert!((image.get(3, 0).r - 1.0).abs() < 0.01);
+        assert!((image.get(7, 0).g - 7.5 / 128.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bad_format() {
+        let data = b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n";
+        assert!(matches!(parse_hdr(&data[..]), Err(ImportError::Parse { line: Some(2), .. })));
+    }
+}
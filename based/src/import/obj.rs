@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::str::{FromStr, SplitWhitespace};
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::hittable::mesh::TriangleMesh;
+use crate::material::Material;
+use crate::texture::ImageTexture;
+use crate::import::{triangulate, ImportError};
+
+/// Material parameters as read from an MTL file.
+#[derive(Debug, Clone)]
+pub struct MtlMaterial {
+    pub name: String,
+    pub kd: Color,
+    pub ks: Color,
+    pub ke: Color,
+    pub ns: f64,
+    pub ni: Option<f64>,
+    pub d: f64,
+    pub illum: u32,
+    pub map_kd: Option<PathBuf>,
+}
+
+impl MtlMaterial {
+    fn new(name: String) -> Self {
+        MtlMaterial {
+            name,
+            kd: Color::new(0.8, 0.8, 0.8),
+            ks: Color::new(0.0, 0.0, 0.0),
+            ke: Color::new(0.0, 0.0, 0.0),
+            ns: 0.0,
+            ni: None,
+            d: 1.0,
+            illum: 2,
+            map_kd: None,
+        }
+    }
+
+    /// Picks the closest `Material` for these parameters. Any `Ke` makes a light, transparent or
+    /// refractive illumination models become glass, mirror models or materials dominated by `Ks`
+    /// become metal with `Ns` driving the fuzz, and everything else is diffuse `Kd`. `map_Kd` is
+    /// ignored, see `load_material`.
+    pub fn to_material(&self) -> Material {
+        let max = |c: Color| c.r.max(c.g).max(c.b);
+        if max(self.ke) > 0.0 {
+            Material::DiffuseLight(Arc::new(self.ke))
+        } else if self.d < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            Material::Dielectric(self.ni.unwrap_or(1.5))
+        } else if self.illum == 3 || max(self.ks) > max(self.kd) {
+            Material::Metal(Arc::new(self.ks), (2.0 / (self.ns + 2.0)).sqrt())
+        } else {
+            Material::Lambertian(Arc::new(self.kd))
+        }
+    }
+
+    /// Like `to_material`, but a diffuse material takes its color from `map_Kd` if that is a
+    /// PNG file. Other image formats are ignored.
+    pub fn load_material(&self) -> Result<Material, ImportError> {
+        match (self.to_material(), &self.map_kd) {
+            (Material::Lambertian(_), Some(path)) if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")) => {
+                let texture = ImageTexture::load(path)
+                    .map_err(|e| ImportError::new(format!("can't load texture {}: {}", path.display(), e)))?;
+                Ok(Material::Lambertian(Arc::new(texture)))
+            }
+            (mat, _) => Ok(mat),
+        }
+    }
+}
+
+/// One group of faces sharing a material, named after the OBJ group or object it came from.
+pub struct ObjMesh {
+    pub name: String,
+    pub material: Option<String>,
+    pub mesh: TriangleMesh,
+}
+
+/// Loads every group in an OBJ file as a separate mesh, resolving `mtllib` relative to the file.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Vec<ObjMesh>, ImportError> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    parse_obj(BufReader::new(file), path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<Vec<MtlMaterial>, ImportError> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    parse_mtl(BufReader::new(file), path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Parses OBJ data, with `base_dir` used to find material libraries.
+pub fn parse_obj(reader: impl BufRead, base_dir: &Path) -> Result<Vec<ObjMesh>, ImportError> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut materials = HashMap::new();
+
+    let mut group = String::from("default");
+    let mut material: Option<String> = None;
+    let mut builders: Vec<MeshBuilder> = Vec::new();
+    let mut current: Option<usize> = None;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line_num = index + 1;
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(parse_vec3(&mut tokens, line_num)?),
+            Some("vn") => normals.push(parse_vec3(&mut tokens, line_num)?),
+            Some("vt") => {
+                let u = parse_next(&mut tokens, line_num)?;
+                let v = tokens.next().map_or(Ok(0.0), |t| parse_token(t, line_num))?;
+                uvs.push((u, v));
+            }
+            Some("g") | Some("o") => {
+                group = tokens.collect::<Vec<_>>().join(" ");
+                current = None;
+            }
+            Some("usemtl") => {
+                material = tokens.next().map(String::from);
+                current = None;
+            }
+            Some("mtllib") => {
+                for lib in tokens {
+                    let path = base_dir.join(lib);
+                    let file = File::open(&path).map_err(|e| {
+                        ImportError::at_line(line_num, format!("can't open material library {}: {}", path.display(), e))
+                    })?;
+                    let dir = path.parent().unwrap_or(base_dir);
+                    for mtl in parse_mtl(BufReader::new(file), dir)? {
+                        materials.insert(mtl.name.clone(), mtl);
+                    }
+                }
+            }
+            Some("f") => {
+                let mut face = Vec::new();
+                for token in tokens {
+                    face.push(parse_face_vertex(token, positions.len(), uvs.len(), normals.len(), line_num)?);
+                }
+                if face.len() < 3 {
+                    return Err(ImportError::at_line(line_num, "face needs at least three vertices"));
+                }
+
+                let builder_index = *current.get_or_insert_with(|| {
+                    builders.iter().position(|b| b.name == group && b.material == material).unwrap_or_else(|| {
+                        builders.push(MeshBuilder::new(group.clone(), material.clone()));
+                        builders.len() - 1
+                    })
+                });
+                let builder = &mut builders[builder_index];
+
+                let polygon = face.iter().map(|v| positions[v.0]).collect::<Vec<_>>();
+                let vertices = face.iter().map(|&v| builder.vertex(v, &positions, &uvs, &normals)).collect::<Vec<_>>();
+                for [a, b, c] in triangulate(&polygon) {
+                    builder.indices.push([vertices[a], vertices[b], vertices[c]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Groups often share materials, so convert each, and load its textures, only once.
+    let mut converted: HashMap<Option<String>, Material> = HashMap::new();
+    let mut meshes = Vec::new();
+    for b in builders.into_iter().filter(|b| !b.indices.is_empty()) {
+        let mat = match converted.get(&b.material) {
+            Some(mat) => mat.clone(),
+            None => {
+                let mat = match b.material.as_ref().and_then(|m| materials.get(m)) {
+                    Some(mtl) => mtl.load_material()?,
+                    None => MtlMaterial::new(String::new()).to_material(),
+                };
+                converted.insert(b.material.clone(), mat.clone());
+                mat
+            }
+        };
+        let mut mesh = TriangleMesh::new(b.positions, b.indices, mat);
+        if b.all_normals {
+            mesh = mesh.normals(b.normals.into_iter().map(Option::unwrap).collect());
+        }
+        if b.all_uvs {
+            mesh = mesh.uvs(b.uvs.into_iter().map(Option::unwrap).collect());
+        }
+        meshes.push(ObjMesh { name: b.name, material: b.material, mesh });
+    }
+    Ok(meshes)
+}
+
+/// Parses MTL data, with `base_dir` used to resolve texture paths.
+pub fn parse_mtl(reader: impl BufRead, base_dir: &Path) -> Result<Vec<MtlMaterial>, ImportError> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line_num = index + 1;
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) if !k.starts_with('#') => k,
+            _ => continue,
+        };
+        if keyword == "newmtl" {
+            let name = tokens.collect::<Vec<_>>().join(" ");
+            materials.push(MtlMaterial::new(name));
+            continue;
+        }
+        let mtl = materials.last_mut()
+            .ok_or_else(|| ImportError::at_line(line_num, format!("`{}` before any `newmtl`", keyword)))?;
+        match keyword {
+            "Kd" => mtl.kd = parse_color(&mut tokens, line_num)?,
+            "Ks" => mtl.ks = parse_color(&mut tokens, line_num)?,
+            "Ke" => mtl.ke = parse_color(&mut tokens, line_num)?,
+            "Ns" => mtl.ns = parse_next(&mut tokens, line_num)?,
+            "Ni" => mtl.ni = Some(parse_next(&mut tokens, line_num)?),
+            "d" => mtl.d = parse_next(&mut tokens, line_num)?,
+            "Tr" => mtl.d = 1.0 - parse_next::<f64>(&mut tokens, line_num)?,
+            "illum" => mtl.illum = parse_next(&mut tokens, line_num)?,
+            // Options such as `-s` may precede the file name, which always comes last.
+            "map_Kd" => mtl.map_kd = tokens.last().map(|f| base_dir.join(f)),
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+// Per-group vertex buffers, with one vertex per distinct position/uv/normal combination.
+struct MeshBuilder {
+    name: String,
+    material: Option<String>,
+    positions: Vec<Vec3>,
+    uvs: Vec<Option<(f64, f64)>>,
+    normals: Vec<Option<Vec3>>,
+    all_uvs: bool,
+    all_normals: bool,
+    indices: Vec<[u32; 3]>,
+    vertex_map: HashMap<FaceVertex, u32>,
+}
+
+type FaceVertex = (usize, Option<usize>, Option<usize>);
+
+impl MeshBuilder {
+    fn new(name: String, material: Option<String>) -> Self {
+        MeshBuilder {
+            name,
+            material,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            all_uvs: true,
+            all_normals: true,
+            indices: Vec::new(),
+            vertex_map: HashMap::new(),
+        }
+    }
+
+    fn vertex(&mut self, v: FaceVertex, positions: &[Vec3], uvs: &[(f64, f64)], normals: &[Vec3]) -> u32 {
+        if let Some(&index) = self.vertex_map.get(&v) {
+            return index;
+        }
+        let index = self.positions.len() as u32;
+        self.positions.push(positions[v.0]);
+        self.uvs.push(v.1.map(|i| uvs[i]));
+        self.normals.push(v.2.map(|i| normals[i]));
+        self.all_uvs &= v.1.is_some();
+        self.all_normals &= v.2.is_some();
+        self.vertex_map.insert(v, index);
+        index
+    }
+}
+
+// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`, turning one-based and negative relative
+// indices into zero-based ones.
+fn parse_face_vertex(token: &str, num_v: usize, num_vt: usize, num_vn: usize, line: usize) -> Result<FaceVertex, ImportError> {
+    let resolve = |s: &str, len: usize| -> Result<usize, ImportError> {
+        let i = parse_token::<i64>(s, line)?;
+        let resolved = if i > 0 { i - 1 } else { len as i64 + i };
+        if i == 0 || resolved < 0 || resolved >= len as i64 {
+            Err(ImportError::at_line(line, format!("index {} out of range, only {} defined", i, len)))
+        } else {
+            Ok(resolved as usize)
+        }
+    };
+    let mut parts = token.split('/');
+    let v = resolve(parts.next().unwrap_or(""), num_v)?;
+    let vt = match parts.next() {
+        Some("") | None => None,
+        Some(s) => Some(resolve(s, num_vt)?),
+    };
+    let vn = match parts.next() {
+        Some("") | None => None,
+        Some(s) => Some(resolve(s, num_vn)?),
+    };
+    Ok((v, vt, vn))
+}
+
+fn parse_token<T: FromStr>(token: &str, line: usize) -> Result<T, ImportError> {
+    token.parse().map_err(|_| ImportError::at_line(line, format!("invalid number `{}`", token)))
+}
+
+fn parse_next<T: FromStr>(tokens: &mut SplitWhitespace, line: usize) -> Result<T, ImportError> {
+    let token = tokens.next().ok_or_else(|| ImportError::at_line(line, "missing value"))?;
+    parse_token(token, line)
+}
+
+fn parse_vec3(tokens: &mut SplitWhitespace, line: usize) -> Result<Vec3, ImportError> {
+    Ok(Vec3::new(parse_next(tokens, line)?, parse_next(tokens, line)?, parse_next(tokens, line)?))
+}
+
+fn parse_color(tokens: &mut SplitWhitespace, line: usize) -> Result<Color, ImportError> {
+    let r = parse_next(tokens, line)?;
+    // A single value means a grey.
+    match tokens.next() {
+        Some(g) => Ok(Color::new(r, parse_token(g, line)?, parse_next(tokens, line)?)),
+        None => Ok(Color::new(r, r, r)),
+    }
+}
+
+#[cfg(test)]
+mod obj_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_obj() {
+        let obj = "\
+# a quad and a concave L shape
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g quad
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g ell
+v 2 0 0
+v 4 0 0
+v 4 1 0
+v 3 1 0
+v 3 3 0
+v 2 3 0
+f -6 -5 -4 -3 -2 -1
+";
+        let meshes = parse_obj(obj.as_bytes(), Path::new("")).unwrap();
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].name, "quad");
+        assert_eq!(meshes[0].mesh.num_triangles(), 2);
+        assert_eq!(meshes[1].name, "ell");
+        assert_eq!(meshes[1].mesh.num_triangles(), 4);
+    }
+
+    #[test]
+    fn test_bad_index() {
+        let obj = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+        match parse_obj(obj.as_bytes(), Path::new("")) {
+            Err(ImportError::Parse { line: Some(3), .. }) => {}
+            _ => panic!("expected a parse error on line 3"),
+        }
+    }
+
+    #[test]
+    fn test_parse_mtl() {
+        let mtl = "\
+newmtl glass
+Ni 1.45
+d 0.1
+newmtl gold
+Kd 0.1 0.1 0.1
+Ks 1.0 0.8 0.3
+Ns 200
+newmtl wall
+Kd 0.7
+map_Kd -s 2 2 1 bricks.png
+newmtl lamp
+Ke 10 10 8
+";
+        let mtls = parse_mtl(mtl.as_bytes(), Path::new("textures")).unwrap();
+        assert!(matches!(mtls[0].to_material(), Material::Dielectric(ir) if ir == 1.45));
+        assert!(matches!(mtls[1].to_material(), Material::Metal(..)));
+        let origin = Vec3::default();
+        assert!(matches!(mtls[2].to_material(), Material::Lambertian(c) if c.value((0.0, 0.0), &origin).g == 0.7));
+        assert_eq!(mtls[2].map_kd, Some(PathBuf::from("textures/bricks.png")));
+        assert!(matches!(mtls[3].to_material(), Material::DiffuseLight(c) if c.value((0.0, 0.0), &origin).b == 8.0));
+    }
+}
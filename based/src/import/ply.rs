@@ -0,0 +1,421 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::hittable::mesh::TriangleMesh;
+use crate::material::Material;
+use crate::import::{triangulate, ImportError};
+
+/// A PLY mesh along with its per-vertex colors, if the file had any.
+pub struct PlyMesh {
+    pub mesh: TriangleMesh,
+    pub colors: Option<Vec<Color>>,
+}
+
+/// Loads the vertices and faces of a PLY file into a mesh made of `mat`.
+pub fn load_ply(path: impl AsRef<Path>, mat: Material) -> Result<PlyMesh, ImportError> {
+    let file = File::open(path)?;
+    parse_ply(BufReader::new(file), mat)
+}
+
+/// Parses ASCII, binary little endian or binary big endian PLY data.
+pub fn parse_ply(mut reader: impl BufRead, mat: Material) -> Result<PlyMesh, ImportError> {
+    let header = Header::parse(&mut reader)?;
+    let mut body: Box<dyn Body> = match header.format {
+        Format::Ascii => {
+            let mut text = String::new();
+            reader.read_to_string(&mut text)?;
+            Box::new(AsciiBody { tokens: text.split_whitespace().map(String::from).collect::<Vec<_>>().into_iter() })
+        }
+        Format::BinaryLittleEndian => Box::new(BinaryBody { reader, big_endian: false }),
+        Format::BinaryBigEndian => Box::new(BinaryBody { reader, big_endian: true }),
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut faces = Vec::new();
+
+    for element in &header.elements {
+        let find = |names: &[&str]| element.properties.iter().position(|p| names.contains(&p.name.as_str()));
+        match element.name.as_str() {
+            "vertex" => {
+                let xyz = [find(&["x"]), find(&["y"]), find(&["z"])];
+                let n = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                let uv = [find(&["u", "s", "texture_u", "texture_s"]), find(&["v", "t", "texture_v", "texture_t"])];
+                let rgb = [find(&["red", "r"]), find(&["green", "g"]), find(&["blue", "b"])];
+                if xyz.iter().any(Option::is_none) {
+                    return Err(ImportError::new("vertex element is missing one of the x, y or z properties"));
+                }
+                let has_normals = n.iter().all(Option::is_some);
+                let has_uvs = uv.iter().all(Option::is_some);
+                let has_colors = rgb.iter().all(Option::is_some);
+
+                for i in 0..element.count {
+                    let values = read_element(body.as_mut(), element).map_err(|e| e.context("vertex", i))?;
+                    let get = |index: Option<usize>| values[index.unwrap()].scalar();
+                    positions.push(Vec3::new(get(xyz[0]), get(xyz[1]), get(xyz[2])));
+                    if has_normals {
+                        normals.push(Vec3::new(get(n[0]), get(n[1]), get(n[2])));
+                    }
+                    if has_uvs {
+                        uvs.push((get(uv[0]), get(uv[1])));
+                    }
+                    if has_colors {
+                        // Integer channels span their whole range, float channels are already in [0, 1].
+                        let scale = |index: Option<usize>| match element.properties[index.unwrap()].ty {
+                            PropertyType::Scalar(ScalarType::U8) => get(index) / u8::MAX as f64,
+                            PropertyType::Scalar(ScalarType::U16) => get(index) / u16::MAX as f64,
+                            _ => get(index),
+                        };
+                        colors.push(Color::new(scale(rgb[0]), scale(rgb[1]), scale(rgb[2])));
+                    }
+                }
+            }
+            "face" => {
+                let indices = find(&["vertex_indices", "vertex_index"])
+                    .ok_or_else(|| ImportError::new("face element has no vertex_indices list"))?;
+                for i in 0..element.count {
+                    let mut values = read_element(body.as_mut(), element).map_err(|e| e.context("face", i))?;
+                    match std::mem::replace(&mut values[indices], Value::Scalar(0.0)) {
+                        Value::List(list) => faces.push(list),
+                        Value::Scalar(_) => return Err(ImportError::new("face vertex_indices must be a list")),
+                    }
+                }
+            }
+            _ => {
+                for i in 0..element.count {
+                    read_element(body.as_mut(), element).map_err(|e| e.context(&element.name, i))?;
+                }
+            }
+        }
+    }
+
+    let mut triangles = Vec::with_capacity(faces.len());
+    for (i, face) in faces.iter().enumerate() {
+        if face.len() < 3 {
+            return Err(ImportError::new(format!("face {} has only {} vertices", i, face.len())));
+        }
+        let face = face.iter().map(|&index| {
+            if index >= 0.0 && (index as usize) < positions.len() {
+                Ok(index as u32)
+            } else {
+                Err(ImportError::new(format!("face {} references vertex {}, but there are only {}", i, index, positions.len())))
+            }
+        }).collect::<Result<Vec<_>, _>>()?;
+        let polygon = face.iter().map(|&index| positions[index as usize]).collect::<Vec<_>>();
+        for [a, b, c] in triangulate(&polygon) {
+            triangles.push([face[a], face[b], face[c]]);
+        }
+    }
+
+    let num_vertices = positions.len();
+    let mut mesh = TriangleMesh::new(positions, triangles, mat);
+    if num_vertices > 0 && normals.len() == num_vertices {
+        mesh = mesh.normals(normals);
+    }
+    if num_vertices > 0 && uvs.len() == num_vertices {
+        mesh = mesh.uvs(uvs);
+    }
+    let colors = if num_vertices > 0 && colors.len() == num_vertices { Some(colors) } else { None };
+    Ok(PlyMesh { mesh, colors })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str, line: usize) -> Result<ScalarType, ImportError> {
+        use self::ScalarType::*;
+        Ok(match name {
+            "char" | "int8" => I8,
+            "uchar" | "uint8" => U8,
+            "short" | "int16" => I16,
+            "ushort" | "uint16" => U16,
+            "int" | "int32" => I32,
+            "uint" | "uint32" => U32,
+            "float" | "float32" => F32,
+            "double" | "float64" => F64,
+            _ => return Err(ImportError::at_line(line, format!("unknown property type `{}`", name))),
+        })
+    }
+
+    fn size(self) -> usize {
+        use self::ScalarType::*;
+        match self {
+            I8 | U8 => 1,
+            I16 | U16 => 2,
+            I32 | U32 | F32 => 4,
+            F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PropertyType {
+    Scalar(ScalarType),
+    // Type of the length prefix, then type of the entries.
+    List(ScalarType, ScalarType),
+}
+
+struct Property {
+    name: String,
+    ty: PropertyType,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+impl Header {
+    fn parse(reader: &mut impl BufRead) -> Result<Header, ImportError> {
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        let mut line = String::new();
+        let mut line_num = 0;
+        loop {
+            line.clear();
+            line_num += 1;
+            if reader.read_line(&mut line)? == 0 {
+                return Err(ImportError::at_line(line_num, "file ended before `end_header`"));
+            }
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next();
+            if line_num == 1 {
+                if keyword != Some("ply") {
+                    return Err(ImportError::at_line(1, "not a PLY file, expected `ply` as the first line"));
+                }
+                continue;
+            }
+            match keyword {
+                Some("format") => {
+                    format = Some(match tokens.next() {
+                        Some("ascii") => Format::Ascii,
+                        Some("binary_little_endian") => Format::BinaryLittleEndian,
+                        Some("binary_big_endian") => Format::BinaryBigEndian,
+                        other => return Err(ImportError::at_line(line_num, format!("unknown format `{}`", other.unwrap_or("")))),
+                    });
+                    if tokens.next() != Some("1.0") {
+                        return Err(ImportError::at_line(line_num, "only PLY version 1.0 is supported"));
+                    }
+                }
+                Some("element") => {
+                    let name = tokens.next()
+                        .ok_or_else(|| ImportError::at_line(line_num, "element has no name"))?;
+                    let count = tokens.next().and_then(|c| c.parse().ok())
+                        .ok_or_else(|| ImportError::at_line(line_num, format!("element `{}` has no valid count", name)))?;
+                    elements.push(Element { name: name.to_string(), count, properties: Vec::new() });
+                }
+                Some("property") => {
+                    let element = elements.last_mut()
+                        .ok_or_else(|| ImportError::at_line(line_num, "property declared before any element"))?;
+                    let ty = match tokens.next() {
+                        Some("list") => {
+                            let count_ty = ScalarType::parse(tokens.next().unwrap_or(""), line_num)?;
+                            if matches!(count_ty, ScalarType::F32 | ScalarType::F64) {
+                                return Err(ImportError::at_line(line_num, "list length must have an integer type"));
+                            }
+                            PropertyType::List(count_ty, ScalarType::parse(tokens.next().unwrap_or(""), line_num)?)
+                        }
+                        Some(ty) => PropertyType::Scalar(ScalarType::parse(ty, line_num)?),
+                        None => return Err(ImportError::at_line(line_num, "property has no type")),
+                    };
+                    let name = tokens.next()
+                        .ok_or_else(|| ImportError::at_line(line_num, "property has no name"))?;
+                    element.properties.push(Property { name: name.to_string(), ty });
+                }
+                Some("end_header") => break,
+                Some("comment") | Some("obj_info") | None => {}
+                Some(other) => return Err(ImportError::at_line(line_num, format!("unexpected `{}` in header", other))),
+            }
+        }
+        let format = format.ok_or_else(|| ImportError::new("header has no `format` line"))?;
+        Ok(Header { format, elements })
+    }
+}
+
+enum Value {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+impl Value {
+    // Lists are nothing we look for where scalars are expected, so they read as zero.
+    fn scalar(&self) -> f64 {
+        match self {
+            Value::Scalar(v) => *v,
+            Value::List(_) => 0.0,
+        }
+    }
+}
+
+fn read_element(body: &mut dyn Body, element: &Element) -> Result<Vec<Value>, BodyError> {
+    element.properties.iter().map(|property| match property.ty {
+        PropertyType::Scalar(ty) => body.read(ty).map(Value::Scalar),
+        PropertyType::List(count_ty, ty) => {
+            let count = body.read(count_ty)? as usize;
+            (0..count).map(|_| body.read(ty)).collect::<Result<_, _>>().map(Value::List)
+        }
+    }).collect()
+}
+
+// Error from reading the body, before it's known which element it happened in.
+struct BodyError(String);
+
+impl BodyError {
+    fn context(self, element: &str, index: usize) -> ImportError {
+        ImportError::new(format!("{} {}: {}", element, index, self.0))
+    }
+}
+
+// Source of property values, independent of the encoding.
+trait Body {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, BodyError>;
+}
+
+struct AsciiBody {
+    tokens: std::vec::IntoIter<String>,
+}
+
+impl Body for AsciiBody {
+    fn read(&mut self, _ty: ScalarType) -> Result<f64, BodyError> {
+        let token = self.tokens.next().ok_or_else(|| BodyError("unexpected end of file".to_string()))?;
+        token.parse().map_err(|_| BodyError(format!("invalid number `{}`", token)))
+    }
+}
+
+struct BinaryBody<R: Read> {
+    reader: R,
+    big_endian: bool,
+}
+
+impl<R: Read> Body for BinaryBody<R> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, BodyError> {
+        use self::ScalarType::*;
+        let mut bytes = [0; 8];
+        let bytes = &mut bytes[..ty.size()];
+        self.reader.read_exact(bytes).map_err(|e| BodyError(e.to_string()))?;
+        if self.big_endian != cfg!(target_endian = "big") {
+            bytes.reverse();
+        }
+        Ok(match ty {
+            I8 => bytes[0] as i8 as f64,
+            U8 => bytes[0] as f64,
+            I16 => i16::from_ne_bytes([bytes[0], bytes[1]]) as f64,
+            U16 => u16::from_ne_bytes([bytes[0], bytes[1]]) as f64,
+            I32 => i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            U32 => u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            F32 => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            F64 => f64::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod ply_tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const HEADER: &str = "\
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    fn material() -> Material {
+        Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5)))
+    }
+
+    fn check(ply: PlyMesh) {
+        assert_eq!(ply.mesh.num_triangles(), 2);
+        let colors = ply.colors.unwrap();
+        assert_eq!(colors.len(), 4);
+        assert_eq!(colors[1].r, 1.0);
+        assert_eq!(colors[1].g, 0.0);
+    }
+
+    #[test]
+    fn test_ascii() {
+        let ply = format!("ply\nformat ascii 1.0\ncomment test quad\n{}\
+0 0 0 255 255 255
+1 0 0 255 0 0
+1 1 0 0 255 0
+0 1 0 0 0 255
+4 0 1 2 3
+", HEADER);
+        check(parse_ply(ply.as_bytes(), material()).unwrap());
+    }
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+        let mut data = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        let vertices = [(0.0f32, 0.0f32, [255u8, 255, 255]), (1.0, 0.0, [255, 0, 0]), (1.0, 1.0, [0, 255, 0]), (0.0, 1.0, [0, 0, 255])];
+        for (x, y, rgb) in vertices.iter() {
+            for v in [*x, *y, 0.0].iter() {
+                data.extend_from_slice(&if big_endian { v.to_be_bytes() } else { v.to_le_bytes() });
+            }
+            data.extend_from_slice(rgb);
+        }
+        data.push(4);
+        for i in 0..4i32 {
+            data.extend_from_slice(&if big_endian { i.to_be_bytes() } else { i.to_le_bytes() });
+        }
+        data
+    }
+
+    #[test]
+    fn test_binary() {
+        check(parse_ply(&binary(false)[..], material()).unwrap());
+        check(parse_ply(&binary(true)[..], material()).unwrap());
+    }
+
+    #[test]
+    fn test_malformed() {
+        let errors = [
+            ("plx\nformat ascii 1.0\nend_header\n", Some(1)),
+            ("ply\nformat ascii 2.0\nend_header\n", Some(2)),
+            ("ply\nformat ascii 1.0\nproperty float x\nend_header\n", Some(3)),
+            ("ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n", Some(4)),
+            ("ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n", Some(5)),
+            ("ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n", None),
+        ];
+        for (ply, line) in errors.iter() {
+            match parse_ply(ply.as_bytes(), material()) {
+                Err(ImportError::Parse { line: l, .. }) => assert_eq!(l, *line, "{}", ply),
+                _ => panic!("expected a parse error for {:?}", ply),
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use ::png::{BitDepth, ColorType, Decoder, DecodingError, Transformations};
+use xenon::color::Color;
+use crate::image::Image;
+use crate::import::ImportError;
+
+/// How the values stored in an image map to linear color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Gamma encoded with the sRGB curve, as almost all color textures are.
+    Srgb,
+    /// Stored as is, as for data such as roughness or normal maps.
+    Linear,
+}
+
+/// Loads an 8 or 16-bit PNG image. Alpha is dropped.
+pub fn load_png(path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Image, ImportError> {
+    let file = File::open(path)?;
+    parse_png(BufReader::new(file), color_space)
+}
+
+/// Parses PNG data of any color type. Palettes and bit depths below 8 are expanded.
+pub fn parse_png(reader: impl Read, color_space: ColorSpace) -> Result<Image, ImportError> {
+    let mut decoder = Decoder::new(reader);
+    decoder.set_transformations(Transformations::EXPAND);
+    let (info, mut reader) = decoder.read_info().map_err(decoding_error)?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer).map_err(decoding_error)?;
+
+    let channels = match info.color_type {
+        ColorType::Grayscale => 1,
+        ColorType::GrayscaleAlpha => 2,
+        ColorType::RGB => 3,
+        ColorType::RGBA => 4,
+        ColorType::Indexed => return Err(ImportError::new("palette wasn't expanded")),
+    };
+    let samples: Vec<f64> = match info.bit_depth {
+        BitDepth::Eight => {
+            // Only 256 possible values, so decode each once.
+            let table = (0..=255).map(|v| decode(v as f64 / 255.0, color_space)).collect::<Vec<_>>();
+            buffer.iter().map(|&v| table[v as usize]).collect()
+        }
+        BitDepth::Sixteen => buffer.chunks_exact(2)
+            .map(|v| decode(u16::from_be_bytes([v[0], v[1]]) as f64 / 65535.0, color_space))
+            .collect(),
+        depth => return Err(ImportError::new(format!("unsupported bit depth {:?}", depth))),
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let row_len = info.line_size / (info.bit_depth as usize / 8);
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in samples.chunks_exact(row_len).take(height) {
+        pixels.extend(row.chunks_exact(channels).take(width).map(|p| match channels {
+            1 | 2 => Color::new(p[0], p[0], p[0]),
+            _ => Color::new(p[0], p[1], p[2]),
+        }));
+    }
+    if pixels.len() != width * height {
+        return Err(ImportError::new("image data ended early"));
+    }
+    Ok(Image::new(width, height, pixels))
+}
+
+/// Converts a stored value in `[0, 1]` to linear.
+fn decode(v: f64, color_space: ColorSpace) -> f64 {
+    match color_space {
+        ColorSpace::Linear => v,
+        ColorSpace::Srgb if v <= 0.04045 => v / 12.92,
+        ColorSpace::Srgb => ((v + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn decoding_error(e: DecodingError) -> ImportError {
+    match e {
+        DecodingError::IoError(e) => ImportError::Io(e),
+        e => ImportError::new(format!("invalid png: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod png_tests {
+    use super::*;
+    use std::io::BufWriter;
+
+    fn encode(width: u32, height: u32, color: ColorType, depth: BitDepth, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let mut encoder = ::png::Encoder::new(BufWriter::new(&mut out), width, height);
+            encoder.set_color(color);
+            encoder.set_depth(depth);
+            encoder.write_header().unwrap().write_image_data(data).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn test_rgb8_srgb() {
+        let data = encode(2, 1, ColorType::RGB, BitDepth::Eight, &[255, 0, 188, 0, 10, 255]);
+        let image = parse_png(data.as_slice(), ColorSpace::Srgb).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        let p = image.get(0, 0);
+        assert_eq!((p.r, p.g), (1.0, 0.0));
+        // sRGB 188 is about half intensity.
+        assert!((p.b - 0.5).abs() < 0.01);
+        assert!((image.get(1, 0).g - 10.0 / 255.0 / 12.92).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_gray16_linear() {
+        let data = encode(1, 2, ColorType::GrayscaleAlpha, BitDepth::Sixteen, &[0x80, 0x00, 0xff, 0xff, 0xff, 0xff, 0, 0]);
+        let image = parse_png(data.as_slice(), ColorSpace::Linear).unwrap();
+        let p = image.get(0, 0);
+        assert_eq!(p.r, 32768.0 / 65535.0);
+        assert_eq!((p.r, p.r), (p.g, p.b));
+        assert_eq!(image.get(0, 1).r, 1.0);
+    }
+
+    #[test]
+    fn test_not_png() {
+        assert!(matches!(parse_png(&b"GIF89a\0\0\0\0\0\0\0\0"[..], ColorSpace::Srgb), Err(ImportError::Parse { .. })));
+    }
+}
@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use crate::import::ImportError;
+use crate::medium::DensityGrid;
+
+/// Loads a raw voxel file of the given resolution, which is nothing but the densities as
+/// little-endian 32-bit floats, x varying fastest, then y, then z.
+pub fn load_raw_grid(path: impl AsRef<Path>, resolution: [usize; 3]) -> Result<DensityGrid, ImportError> {
+    let file = File::open(path)?;
+    parse_raw_grid(BufReader::new(file), resolution)
+}
+
+/// Parses raw voxel data as described in `load_raw_grid`.
+pub fn parse_raw_grid(reader: impl Read, resolution: [usize; 3]) -> Result<DensityGrid, ImportError> {
+    let [nx, ny, nz] = resolution;
+    if nx == 0 || ny == 0 || nz == 0 {
+        return Err(ImportError::new(format!("a {}x{}x{} grid has no cells", nx, ny, nz)));
+    }
+    let expected = nx.checked_mul(ny).and_then(|n| n.checked_mul(nz)).and_then(|n| n.checked_mul(4))
+        .ok_or_else(|| ImportError::new(format!("a {}x{}x{} grid is too large", nx, ny, nz)))?;
+
+    // Reads no more than one byte past the grid, enough to tell the file is too long,
+    // without trusting the resolution with an allocation up front.
+    let mut bytes = Vec::new();
+    reader.take(expected as u64 + 1).read_to_end(&mut bytes)?;
+    if bytes.len() != expected {
+        return Err(ImportError::new(format!(
+            "expected {} bytes for a {}x{}x{} grid, found {}{}",
+            expected, nx, ny, nz, bytes.len(), if bytes.len() > expected { " or more" } else { "" },
+        )));
+    }
+
+    let values = bytes.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect::<Vec<_>>();
+    if let Some(i) = values.iter().position(|v| !v.is_finite() || *v < 0.0) {
+        return Err(ImportError::new(format!("invalid density {} at voxel {}", values[i], i)));
+    }
+    Ok(DensityGrid::new(resolution, values))
+}
+
+#[cfg(test)]
+mod raw_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_raw() {
+        let values = [0.0f32, 0.5, 1.0, 2.0, 0.25, 0.0];
+        let bytes = values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>();
+        let grid = parse_raw_grid(&bytes[..], [3, 2, 1]).unwrap();
+        assert_eq!(grid.get(2, 0, 0), 1.0);
+        assert_eq!(grid.get(0, 1, 0), 2.0);
+        assert_eq!(grid.max(), 2.0);
+
+        assert!(parse_raw_grid(&bytes[..], [2, 2, 2]).is_err());
+        let negative = (-1.0f32).to_le_bytes();
+        assert!(parse_raw_grid(&negative[..], [1, 1, 1]).is_err());
+        assert!(parse_raw_grid(&[][..], [0, 4, 4]).is_err());
+        assert!(parse_raw_grid(&bytes[..], [usize::MAX, 2, 1]).is_err());
+    }
+}
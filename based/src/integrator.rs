@@ -0,0 +1,270 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use glitz::vec::Vec3;
+use rand::Rng;
+use xenon::color::Color;
+use crate::bsdf::Bsdf;
+use crate::environment::Environment;
+use crate::hittable::{Hit, Hittable};
+use crate::light::{Lights, power_heuristic};
+use crate::medium::{HenyeyGreenstein, Medium};
+use crate::random::with_rng;
+use crate::ray::Ray;
+
+pub mod path;
+pub mod direct;
+pub mod ambient_occlusion;
+pub mod debug;
+
+pub use self::path::PathTracer;
+pub use self::direct::DirectLighting;
+pub use self::ambient_occlusion::AmbientOcclusion;
+pub use self::debug::{DebugMode, DebugView};
+
+/// A way of working out how much light arrives at the camera along a ray.
+pub trait Integrator: Send + Sync {
+    /// Estimate of the radiance arriving at the origin of camera ray `r`.
+    fn li(&self, r: Ray, scene: &Scene) -> Color;
+}
+
+/// What an `Integrator` sees of the scene being rendered.
+pub struct Scene<'a> {
+    pub world: &'a dyn Hittable,
+    pub environment: &'a Environment,
+    pub lights: &'a Lights<'a>,
+    /// Medium filling all space outside any `Volume`.
+    pub medium: Option<&'a dyn Medium>,
+    num_rays: &'a AtomicUsize,
+}
+
+/// Where light arriving along a ray last interacted with the scene.
+// One is made per bounce and matched on straight away, so boxing the hit would only add an
+// allocation to the innermost loop.
+#[allow(clippy::large_enum_variant)]
+pub enum Interaction<'a> {
+    Surface(Hit<'a>),
+    /// Scattered by a medium at `point`.
+    Medium { point: Vec3, phase: HenyeyGreenstein },
+    /// Came from the environment.
+    Escaped,
+}
+
+impl<'a> Scene<'a> {
+    pub fn new(world: &'a dyn Hittable, environment: &'a Environment, lights: &'a Lights<'a>, medium: Option<&'a dyn Medium>, num_rays: &'a AtomicUsize) -> Self {
+        Scene { world, environment, lights, medium, num_rays }
+    }
+
+    /// Closest hit along `r`, with its footprint worked out from `r`'s differentials.
+    pub fn intersect(&self, r: &Ray) -> Option<Hit<'a>> {
+        self.num_rays.fetch_add(1, Ordering::Relaxed);
+        let mut hit = self.world.intersect(r, 0.00001, f64::INFINITY)?;
+        hit.compute_footprint(r);
+        hit.time = r.time;
+        Some(hit)
+    }
+
+    /// Follows `r` through `medium`, and any others it passes into through interfaces, to
+    /// where light arriving along it last scattered, updating `medium` to the one there.
+    /// Returns that along with the weight to multiply the path's throughput by.
+    pub fn trace(&self, r: &Ray, medium: &mut Option<&'a dyn Medium>) -> (Interaction<'a>, Color) {
+        let mut weight = Color::new(1.0, 1.0, 1.0);
+        let mut r = Ray::new(r.o, r.d).time(r.time).differential(r.differential);
+        loop {
+            let hit = self.intersect(&r);
+            if let Some(m) = *medium {
+                let sample = m.sample(&r, hit.as_ref().map_or(f64::INFINITY, |hit| hit.t));
+                weight = weight * sample.weight;
+                if let Some(t) = sample.t {
+                    return (Interaction::Medium { point: r.at(t), phase: m.phase() }, weight);
+                }
+            }
+            match hit {
+                Some(hit) if hit.mat.is_interface() => {
+                    // Keeps the differentials, which still describe the same straight line.
+                    *medium = self.medium_along(&hit, &r.d, *medium);
+                    r = Ray::new(hit.point, r.d).time(r.time).differential(r.differential);
+                }
+                Some(hit) => return (Interaction::Surface(hit), weight),
+                None => return (Interaction::Escaped, weight),
+            }
+        }
+    }
+
+    /// Medium light leaving `hit` along `d` travels through, given that `current` is the one
+    /// on the side the hit was made from.
+    pub fn medium_along(&self, hit: &Hit<'a>, d: &Vec3, current: Option<&'a dyn Medium>) -> Option<&'a dyn Medium> {
+        match hit.medium {
+            Some(inside) if d.dot(&hit.normal) < 0.0 => if hit.front_face { Some(inside) } else { self.medium },
+            _ => current,
+        }
+    }
+
+    /// Whether anything lies between `origin` and `dist` along `d` at `time`.
+    pub fn occluded(&self, origin: Vec3, d: Vec3, dist: f64, time: f64) -> bool {
+        self.num_rays.fetch_add(1, Ordering::Relaxed);
+        self.world.intersect(&Ray::new(origin, d).time(time), 0.00001, dist * (1.0 - 0.00001)).is_some()
+    }
+
+    /// Light emitted by `hit` back along `r`. `bsdf_pdf` is the density with which the last
+    /// bounce picked `r`, or `None` if light sampling couldn't have found `hit`, which
+    /// weights the emission against sampling the same light directly.
+    pub fn emitted(&self, hit: &Hit, r: &Ray, bsdf_pdf: Option<f64>) -> Color {
+        let emitted = hit.mat.emitted(hit);
+        match bsdf_pdf {
+            Some(pdf) => emitted * power_heuristic(pdf, self.lights.pdf(&r.o, hit)),
+            None => emitted,
+        }
+    }
+
+    /// Light from the environment along `r`, which escaped the scene, weighted as in `emitted`.
+    pub fn escaped(&self, r: &Ray, bsdf_pdf: Option<f64>) -> Color {
+        let radiance = self.environment.radiance(&r.d);
+        match bsdf_pdf {
+            Some(pdf) => radiance * power_heuristic(pdf, self.lights.pdf_environment(&r.d)),
+            None => radiance,
+        }
+    }
+
+    /// Fraction of light getting from `dist` along `d` back to `origin` at `time`, through
+    /// `medium` and any others past interfaces on the way, or zero if a surface is in the way.
+    pub fn transmittance(&self, origin: Vec3, d: Vec3, dist: f64, time: f64, mut medium: Option<&'a dyn Medium>) -> Color {
+        let mut tr = Color::new(1.0, 1.0, 1.0);
+        let mut r = Ray::new(origin, d).time(time);
+        let mut remaining = dist * (1.0 - 0.00001);
+        loop {
+            self.num_rays.fetch_add(1, Ordering::Relaxed);
+            let hit = self.world.intersect(&r, 0.00001, remaining);
+            if let Some(m) = medium {
+                tr = tr * m.transmittance(&r, hit.as_ref().map_or(remaining, |hit| hit.t));
+            }
+            match hit {
+                Some(hit) if hit.mat.is_interface() => {
+                    medium = self.medium_along(&hit, &d, medium);
+                    remaining -= hit.t;
+                    r = Ray::new(hit.point, d).time(time);
+                }
+                Some(_) => return Color::new(0.0, 0.0, 0.0),
+                None => return tr,
+            }
+        }
+    }
+
+    /// Direct light reaching `hit` from a point sampled on a light and scattered by `bsdf`
+    /// towards `wo`, weighted against the chance of the BSDF sampling the same direction.
+    /// `medium` is the one on the side of `hit` that `wo` points to.
+    pub fn sample_light(&self, hit: &Hit<'a>, bsdf: &Bsdf, wo: &Vec3, medium: Option<&'a dyn Medium>) -> Color {
+        self.light_scattered(hit.point, hit.time, |wi| self.medium_along(hit, wi, medium), |wi| (bsdf.eval(wo, wi), bsdf.pdf(wo, wi)))
+    }
+
+    /// Direct light reaching `point` inside `medium` from a sampled point on a light, and
+    /// scattered by `phase` towards `wo`, weighted as in `sample_light`.
+    pub fn sample_light_medium(&self, point: Vec3, time: f64, phase: &HenyeyGreenstein, wo: &Vec3, medium: Option<&'a dyn Medium>) -> Color {
+        self.light_scattered(point, time, |_| medium, |wi| {
+            let p = phase.eval(wo, wi);
+            (Color::new(p, p, p), p)
+        })
+    }
+
+    /// Light from a sampled point on a light scattered at `point`, where `medium` gives the
+    /// medium light arrives through along each direction, and `scatter` how much of it is
+    /// scattered and how likely it would have been to sample the direction otherwise.
+    fn light_scattered(&self, point: Vec3, time: f64, medium: impl Fn(&Vec3) -> Option<&'a dyn Medium>, scatter: impl Fn(&Vec3) -> (Color, f64)) -> Color {
+        let (u_light, u) = with_rng(|rng| (rng.gen(), (rng.gen(), rng.gen())));
+        let sample = match self.lights.sample(&point, u_light, u) {
+            Some(sample) => sample,
+            None => return Color::new(0.0, 0.0, 0.0),
+        };
+
+        let (f, scatter_pdf) = scatter(&sample.wi);
+        if f.r + f.g + f.b == 0.0 || sample.radiance.r + sample.radiance.g + sample.radiance.b == 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let tr = self.transmittance(point, sample.wi, sample.dist, time, medium(&sample.wi));
+        if tr.r + tr.g + tr.b == 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let weight = power_heuristic(sample.pdf, scatter_pdf);
+        f * tr * sample.radiance * (weight / sample.pdf)
+    }
+}
+
+#[cfg(test)]
+mod integrator_tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::hittable::{HittableList, Sphere};
+    use crate::hittable::instance::Instance;
+    use crate::hittable::volume::Volume;
+    use crate::material::Material;
+    use glitz::transform::Transform;
+    use crate::medium::Homogeneous;
+
+    /// Averages `samples` estimates of the light coming back along a ray straight at
+    /// `object`, alone at the origin and lit evenly by a white environment.
+    fn furnace(integrator: &dyn Integrator, object: impl Hittable + 'static, samples: usize) -> f64 {
+        let mut world = HittableList::new();
+        world.add(object);
+        let environment = Environment::Constant(Color::new(1.0, 1.0, 1.0));
+        let lights = Lights::new(&world, &environment);
+        let num_rays = AtomicUsize::new(0);
+        let scene = Scene::new(&world, &environment, &lights, None, &num_rays);
+        let r = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        (0..samples).map(|_| integrator.li(Ray::new(r.o, r.d), &scene).g).sum::<f64>() / samples as f64
+    }
+
+    fn sphere(mat: Material) -> Sphere {
+        Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, mat)
+    }
+
+    #[test]
+    fn test_furnace() {
+        // A convex diffuse object sees nothing but the environment, so reflects its albedo.
+        let grey = || sphere(Material::Lambertian(Arc::new(Color::new(0.5, 0.5, 0.5))));
+        for integrator in [&PathTracer::new() as &dyn Integrator, &DirectLighting::new()] {
+            let estimate = furnace(integrator, grey(), 20_000);
+            assert!((estimate - 0.5).abs() < 0.01, "{}", estimate);
+        }
+        assert_eq!(furnace(&AmbientOcclusion::new(4, f64::INFINITY), grey(), 100), 1.0);
+    }
+
+    #[test]
+    fn test_furnace_volume() {
+        // Light that is only scattered, never absorbed, all gets out again in the end, and
+        // some of it gets through without scattering.
+        let white = Color::new(1.0, 1.0, 1.0);
+        let medium = || Homogeneous::new(Color::new(0.0, 0.0, 0.0), white).anisotropy(0.5);
+        let estimate = furnace(&PathTracer::new(), Volume::new(sphere(Material::Interface), medium()), 20_000);
+        assert!((estimate - 1.0).abs() < 0.01, "{}", estimate);
+
+        // Behind glass, it is lost only to refraction.
+        let glass = furnace(&PathTracer::new(), sphere(Material::Dielectric(1.5)), 20_000);
+        let milky = furnace(&PathTracer::new(), Volume::new(sphere(Material::Dielectric(1.5)), medium()), 20_000);
+        assert!((glass - 1.0).abs() < 0.01 && (milky - 1.0).abs() < 0.02, "{} {}", glass, milky);
+
+        // Single scattering alone is less.
+        let direct = furnace(&DirectLighting::new(), Volume::new(sphere(Material::Interface), medium()), 20_000);
+        assert!(direct > 0.3 && direct < 0.9, "{}", direct);
+    }
+
+    #[test]
+    fn test_unsampled_emitters() {
+        // Emission found by the BSDF is only weighted down where sampling the lights could
+        // have found it too, or the rest would be lost.
+        let glow = || sphere(Material::DiffuseLight(Arc::new(Color::new(1.0, 1.0, 1.0))));
+        let mut world = HittableList::new();
+        world.add(glow());
+        world.add(Instance::new(Arc::new(glow()), Transform::translate(Vec3::new(3.0, 0.0, 0.0))));
+        let environment = Environment::None;
+        let lights = Lights::new(&world, &environment);
+        let num_rays = AtomicUsize::new(0);
+        let scene = Scene::new(&world, &environment, &lights, None, &num_rays);
+
+        for (x, sampled) in [(0.0, true), (3.0, false)] {
+            let r = Ray::new(Vec3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            let hit = scene.intersect(&r).unwrap();
+            let emitted = scene.emitted(&hit, &r, Some(0.1)).g;
+            assert_eq!(emitted < 1.0, sampled, "{} {}", x, emitted);
+            assert_eq!(lights.pdf(&r.o, &hit) > 0.0, sampled);
+        }
+    }
+}
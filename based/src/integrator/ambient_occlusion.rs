@@ -0,0 +1,38 @@
+use rand::Rng;
+use xenon::color::Color;
+use crate::integrator::{Integrator, Scene};
+use crate::random::with_rng;
+use crate::ray::Ray;
+use crate::sampling::cosine_hemisphere;
+
+/// How much of the hemisphere above the first hit is open, as a gray level, ignoring
+/// materials and lights altogether. Rays that miss everything are black.
+pub struct AmbientOcclusion {
+    samples: u16,
+    /// Occluders further away than this don't count.
+    distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: u16, distance: f64) -> Self {
+        AmbientOcclusion { samples, distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn li(&self, r: Ray, scene: &Scene) -> Color {
+        let hit = match scene.intersect(&r) {
+            Some(hit) => hit,
+            None => return Color::new(0.0, 0.0, 0.0),
+        };
+        // Directions are cosine weighted, so counting unoccluded ones gives the cosine
+        // weighted fraction without further weights.
+        let frame = hit.shading_frame();
+        let open = (0..self.samples).filter(|_| {
+            let wi = frame.to_world(&cosine_hemisphere(with_rng(|rng| (rng.gen(), rng.gen()))));
+            !scene.occluded(hit.point, wi, self.distance, hit.time)
+        }).count();
+        let ao = open as f64 / self.samples.max(1) as f64;
+        Color::new(ao, ao, ao)
+    }
+}
@@ -0,0 +1,125 @@
+use xenon::color::Color;
+use crate::hittable::bvh::nodes_visited;
+use crate::integrator::{Integrator, Scene};
+use crate::ray::Ray;
+
+/// What `DebugView` shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugMode {
+    /// Outward facing shading normals, mapped from `[-1, 1]` to `[0, 1]` per axis.
+    Normals,
+    /// Distance from the camera, as a fraction of the given distance beyond which
+    /// everything is white.
+    Depth(f64),
+    /// The material's albedo, without lighting.
+    Albedo,
+    /// Texture coordinates in red and green, wrapped into `[0, 1)`.
+    Uv,
+    /// A color per object, where every triangle of a mesh counts as the same object and
+    /// every instance of one as a different one.
+    ObjectId,
+    /// A color per material.
+    MaterialId,
+    /// The number of BVH nodes visited to find the first hit, from black for none through
+    /// blue, green and yellow to red at the given count and beyond. Unlike the others, it
+    /// shows rays that miss everything too.
+    Heatmap(u32),
+}
+
+impl DebugMode {
+    /// Short name to tell images of different modes apart by.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugMode::Normals => "normals",
+            DebugMode::Depth(_) => "depth",
+            DebugMode::Albedo => "albedo",
+            DebugMode::Uv => "uv",
+            DebugMode::ObjectId => "object_id",
+            DebugMode::MaterialId => "material_id",
+            DebugMode::Heatmap(_) => "heatmap",
+        }
+    }
+}
+
+/// Shows a property of the first hit instead of lighting, for inspecting geometry.
+/// Rays that miss everything are black.
+pub struct DebugView {
+    mode: DebugMode,
+}
+
+impl DebugView {
+    pub fn new(mode: DebugMode) -> Self {
+        DebugView { mode }
+    }
+}
+
+impl Integrator for DebugView {
+    fn li(&self, r: Ray, scene: &Scene) -> Color {
+        let nodes_before = nodes_visited();
+        let hit = scene.intersect(&r);
+        if let DebugMode::Heatmap(max) = self.mode {
+            return heat((nodes_visited() - nodes_before) as f64 / max as f64);
+        }
+        let hit = match hit {
+            Some(hit) => hit,
+            None => return Color::new(0.0, 0.0, 0.0),
+        };
+        match self.mode {
+            DebugMode::Normals => {
+                let n = if hit.front_face { hit.normal } else { -hit.normal };
+                Color::new(0.5 * (n.x + 1.0), 0.5 * (n.y + 1.0), 0.5 * (n.z + 1.0))
+            }
+            DebugMode::Depth(max) => {
+                let depth = hit.t * r.d.length() / max;
+                Color::new(depth, depth, depth)
+            }
+            DebugMode::Albedo => hit.mat.albedo(&hit),
+            DebugMode::Uv => Color::new(hit.uv.0.rem_euclid(1.0), hit.uv.1.rem_euclid(1.0), 0.0),
+            DebugMode::ObjectId => id_color(hit.object),
+            DebugMode::MaterialId => id_color(hit.mat.id()),
+            DebugMode::Heatmap(_) => unreachable!(),
+        }
+    }
+}
+
+/// A bright, arbitrary but consistent color for `id`.
+fn id_color(id: u64) -> Color {
+    // The SplitMix64 finalizer, so that nearby ids get unrelated colors.
+    let mut x = id.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^= x >> 31;
+    let channel = |shift: u64| 0.2 + 0.8 * ((x >> shift) & 0xff) as f64 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
+
+/// Maps `t` in `[0, 1]` onto a ramp from black through blue, green and yellow to red.
+fn heat(t: f64) -> Color {
+    const RAMP: [(f64, f64, f64); 5] = [(0.0, 0.0, 0.0), (0.0, 0.0, 1.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0), (1.0, 0.0, 0.0)];
+    let x = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as f64;
+    let i = (x as usize).min(RAMP.len() - 2);
+    let f = x - i as f64;
+    let (a, b) = (RAMP[i], RAMP[i + 1]);
+    Color::new(a.0 + f * (b.0 - a.0), a.1 + f * (b.1 - a.1), a.2 + f * (b.2 - a.2))
+}
+
+#[cfg(test)]
+mod debug_tests {
+    use super::*;
+
+    #[test]
+    fn test_heat() {
+        let (cold, hot) = (heat(0.0), heat(2.0));
+        assert_eq!((cold.r, cold.g, cold.b), (0.0, 0.0, 0.0));
+        assert_eq!((hot.r, hot.g, hot.b), (1.0, 0.0, 0.0));
+        let middle = heat(0.5);
+        assert_eq!((middle.r, middle.g, middle.b), (0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_id_color() {
+        let (a, b) = (id_color(1), id_color(2));
+        assert!((a.r, a.g, a.b) != (b.r, b.g, b.b));
+        assert_eq!(id_color(1).r, a.r);
+    }
+}
@@ -0,0 +1,85 @@
+use rand::Rng;
+use xenon::color::Color;
+use crate::integrator::{Interaction, Integrator, Scene};
+use crate::random::with_rng;
+use crate::ray::Ray;
+
+/// Only light that reaches the camera after at most one diffuse or glossy bounce or one
+/// scattering in a medium, plus whatever is seen in mirrors and through glass along the
+/// way. Fast and noise free compared to `PathTracer`, but without any indirect light.
+pub struct DirectLighting {
+    max_depth: u16,
+}
+
+impl DirectLighting {
+    pub fn new() -> Self {
+        DirectLighting { max_depth: 8 }
+    }
+
+    /// Number of delta bounces followed before giving up.
+    pub fn max_depth(self, max_depth: u16) -> Self {
+        DirectLighting { max_depth }
+    }
+}
+
+impl Default for DirectLighting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn li(&self, mut r: Ray, scene: &Scene) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut bsdf_pdf: Option<f64> = None;
+        let mut medium = scene.medium;
+
+        for _ in 0..=self.max_depth {
+            let (interaction, weight) = scene.trace(&r, &mut medium);
+            throughput = throughput * weight;
+            let hit = match interaction {
+                Interaction::Surface(hit) => hit,
+                Interaction::Escaped => {
+                    color += throughput * scene.escaped(&r, bsdf_pdf);
+                    break;
+                }
+                Interaction::Medium { .. } if bsdf_pdf.is_some() => break,
+                Interaction::Medium { point, phase } => {
+                    // Light scattered once in a medium counts as direct too.
+                    let wo = -r.d.unit_vec();
+                    color += throughput * scene.sample_light_medium(point, r.time, &phase, &wo, medium);
+                    let (wi, pdf) = phase.sample(&wo, with_rng(|rng| (rng.gen(), rng.gen())));
+                    bsdf_pdf = Some(pdf);
+                    r = Ray::new(point, wi).time(r.time);
+                    continue;
+                }
+            };
+            color += throughput * scene.emitted(&hit, &r, bsdf_pdf);
+            if bsdf_pdf.is_some() {
+                // This was the BSDF sampled half of the light at the last hit.
+                break;
+            }
+
+            let bsdf = match hit.mat.bsdf(&hit) {
+                Some(bsdf) => bsdf,
+                None => break,
+            };
+            let wo = -r.d;
+            if !bsdf.is_delta() {
+                color += throughput * scene.sample_light(&hit, &bsdf, &wo, medium);
+            }
+
+            let (u_lobe, u) = with_rng(|rng| (rng.gen(), (rng.gen(), rng.gen())));
+            let sample = match bsdf.sample(&wo, u_lobe, u) {
+                Some(sample) => sample,
+                None => break,
+            };
+            throughput = throughput * sample.weight;
+            bsdf_pdf = if sample.is_delta { None } else { Some(sample.pdf) };
+            medium = scene.medium_along(&hit, &sample.wi, medium);
+            r = sample.ray(&hit, &r);
+        }
+        color
+    }
+}
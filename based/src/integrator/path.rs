@@ -0,0 +1,104 @@
+use rand::Rng;
+use xenon::color::Color;
+use crate::integrator::{Interaction, Integrator, Scene};
+use crate::random::with_rng;
+use crate::ray::Ray;
+
+/// Unidirectional path tracing with light sampling at every non-delta bounce, combined with
+/// BSDF sampling by multiple importance sampling. Media are sampled by free-flight tracking,
+/// scattering light just like surfaces. Paths end by Russian roulette.
+pub struct PathTracer {
+    min_depth: u16,
+    max_depth: u16,
+}
+
+impl PathTracer {
+    pub fn new() -> Self {
+        PathTracer { min_depth: 3, max_depth: 100 }
+    }
+
+    /// Number of bounces every path makes, unless it leaves the scene or is absorbed, before
+    /// Russian roulette may end it.
+    pub fn min_depth(self, min_depth: u16) -> Self {
+        PathTracer { min_depth, ..self }
+    }
+
+    /// Number of rays after which a path is cut off regardless, which only matters for
+    /// scenes where light bounces around without losing much, like a closed white box.
+    pub fn max_depth(self, max_depth: u16) -> Self {
+        PathTracer { max_depth, ..self }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integrator for PathTracer {
+    fn li(&self, mut r: Ray, scene: &Scene) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        // Undoes the scaling of radiance by refraction, which would otherwise skew roulette
+        // against paths inside dense media.
+        let mut eta_scale = 1.0;
+        // The density with which the last bounce picked `r`, or `None` if it was a camera ray
+        // or a delta bounce that light sampling couldn't have produced.
+        let mut bsdf_pdf: Option<f64> = None;
+        let mut medium = scene.medium;
+
+        for depth in 0..self.max_depth {
+            let (interaction, weight) = scene.trace(&r, &mut medium);
+            throughput = throughput * weight;
+            match interaction {
+                Interaction::Escaped => {
+                    color += throughput * scene.escaped(&r, bsdf_pdf);
+                    break;
+                }
+                Interaction::Medium { point, phase } => {
+                    let wo = -r.d.unit_vec();
+                    color += throughput * scene.sample_light_medium(point, r.time, &phase, &wo, medium);
+                    // The phase function is sampled exactly, so the throughput stays the same.
+                    let (wi, pdf) = phase.sample(&wo, with_rng(|rng| (rng.gen(), rng.gen())));
+                    bsdf_pdf = Some(pdf);
+                    r = Ray::new(point, wi).time(r.time);
+                }
+                Interaction::Surface(hit) => {
+                    color += throughput * scene.emitted(&hit, &r, bsdf_pdf);
+
+                    let bsdf = match hit.mat.bsdf(&hit) {
+                        Some(bsdf) => bsdf,
+                        None => break,
+                    };
+                    let wo = -r.d;
+                    if !bsdf.is_delta() {
+                        color += throughput * scene.sample_light(&hit, &bsdf, &wo, medium);
+                    }
+
+                    let (u_lobe, u) = with_rng(|rng| (rng.gen(), (rng.gen(), rng.gen())));
+                    let sample = match bsdf.sample(&wo, u_lobe, u) {
+                        Some(sample) => sample,
+                        None => break,
+                    };
+                    throughput = throughput * sample.weight;
+                    eta_scale *= sample.eta * sample.eta;
+                    bsdf_pdf = if sample.is_delta { None } else { Some(sample.pdf) };
+                    medium = scene.medium_along(&hit, &sample.wi, medium);
+                    r = sample.ray(&hit, &r);
+                }
+            }
+
+            // Russian roulette: past the first few bounces, end paths at random with a chance
+            // that grows as they carry less light, and make up for it in those that go on.
+            if depth + 1 >= self.min_depth {
+                let survival = (throughput.r.max(throughput.g).max(throughput.b) * eta_scale).min(1.0);
+                if with_rng(Rng::gen::<f64>) >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+        color
+    }
+}
@@ -9,4 +9,14 @@ pub mod ray;
 pub mod camera;
 pub mod random;
 pub mod renderer;
+pub mod import;
+pub mod light;
+pub mod environment;
+pub mod sampling;
+pub mod image;
+pub mod texture;
+pub mod microfacet;
+pub mod bsdf;
+pub mod integrator;
+pub mod medium;
 
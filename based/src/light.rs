@@ -0,0 +1,118 @@
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::hittable::{Hit, Hittable};
+use crate::environment::{Environment, EnvironmentMap};
+
+/// Light arriving at some point in the scene from a sampled direction.
+pub struct LightSample {
+    /// Unit direction from the receiving point towards the light.
+    pub wi: Vec3,
+    /// Distance to the light along `wi`, infinite for the environment.
+    pub dist: f64,
+    pub radiance: Color,
+    /// Density of having picked `wi`, with respect to solid angle at the receiving point.
+    pub pdf: f64,
+}
+
+/// Everything in a scene that emits light. Emissive primitives are picked with probability
+/// proportional to their area, so that every point on every light is equally likely, while
+/// an environment map, if there is one, gets half of all samples.
+pub struct Lights<'a> {
+    prims: Vec<&'a dyn Hittable>,
+    cdf: Vec<f64>,
+    total_area: f64,
+    environment: Option<&'a EnvironmentMap>,
+    environment_prob: f64,
+}
+
+impl<'a> Lights<'a> {
+    pub fn new(world: &'a dyn Hittable, environment: &'a Environment) -> Self {
+        let mut prims = Vec::new();
+        world.collect_lights(&mut prims);
+
+        let mut total_area = 0.0;
+        let cdf = prims.iter().map(|p| {
+            total_area += p.area();
+            total_area
+        }).collect();
+
+        let environment = match environment {
+            Environment::Map(map) => Some(map.as_ref()),
+            _ => None,
+        };
+        let environment_prob = match environment {
+            Some(_) if total_area > 0.0 => 0.5,
+            Some(_) => 1.0,
+            None => 0.0,
+        };
+
+        Lights { prims, cdf, total_area, environment, environment_prob }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_area == 0.0 && self.environment.is_none()
+    }
+
+    /// Picks a point on some light, as seen from `origin`. `u_light` chooses the light and `u`
+    /// the point on it, all uniform in `[0, 1)`. Returns `None` if the back of the light faces `origin`.
+    pub fn sample(&self, origin: &Vec3, u_light: f64, u: (f64, f64)) -> Option<LightSample> {
+        if let Some(environment) = self.environment {
+            if u_light < self.environment_prob {
+                let (wi, radiance, pdf) = environment.sample(u)?;
+                return Some(LightSample { wi, dist: f64::INFINITY, radiance, pdf: pdf * self.environment_prob });
+            }
+        }
+        if self.total_area == 0.0 {
+            return None;
+        }
+
+        let u_light = (u_light - self.environment_prob) / (1.0 - self.environment_prob);
+        let target = u_light * self.total_area;
+        let index = self.cdf.partition_point(|&c| c <= target).min(self.prims.len() - 1);
+        let hit = self.prims[index].sample_surface(u)?;
+
+        let to_light = hit.point - *origin;
+        let dist = to_light.length();
+        let pdf = self.pdf_area_to_solid_angle(origin, &hit);
+        if pdf > 0.0 {
+            Some(LightSample { wi: to_light / dist, dist, radiance: hit.mat.emitted(&hit), pdf })
+        } else {
+            None
+        }
+    }
+
+    /// Solid angle density with which `sample` would return the light point `hit` as seen from
+    /// `origin`, zero for emissive surfaces that aren't among the lights.
+    pub fn pdf(&self, origin: &Vec3, hit: &Hit) -> f64 {
+        if self.total_area == 0.0 || !hit.is_light || !hit.mat.is_emissive() {
+            0.0
+        } else {
+            self.pdf_area_to_solid_angle(origin, hit)
+        }
+    }
+
+    /// Solid angle density with which `sample` would return the environment along `d`.
+    pub fn pdf_environment(&self, d: &Vec3) -> f64 {
+        self.environment.map_or(0.0, |e| e.pdf(d) * self.environment_prob)
+    }
+
+    fn pdf_area_to_solid_angle(&self, origin: &Vec3, hit: &Hit) -> f64 {
+        let to_light = hit.point - *origin;
+        let dist_squared = to_light.dot(&to_light);
+        // Lights only emit from their front, where the sampled normal points.
+        let cos_light = -hit.normal.dot(&to_light) / dist_squared.sqrt();
+        if cos_light <= 0.0 {
+            0.0
+        } else {
+            dist_squared / (cos_light * self.total_area) * (1.0 - self.environment_prob)
+        }
+    }
+}
+
+/// Weight for one of two sampling strategies that each take one sample, given both of their densities.
+#[inline]
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+    if a + b == 0.0 { 0.0 } else { a / (a + b) }
+}
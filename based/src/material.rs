@@ -1,62 +1,217 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::sync::Arc;
 use glitz::vec::Vec3;
+use crate::bsdf::{Bsdf, BsdfSample, Bxdf, DielectricBxdf, FuzzyMirrorBxdf, LambertianBxdf};
 use crate::hittable::Hit;
-use crate::ray::Ray;
-use crate::random::with_rng;
+use crate::microfacet::{fresnel_conductor, TrowbridgeReitz};
 use xenon::color::Color;
+use crate::texture::Texture;
+use self::principled::Principled;
+
+pub mod principled;
+
+/// Evaluates `texture` at `hit`, filtered over its footprint.
+#[inline]
+fn lookup(texture: &dyn Texture, hit: &Hit) -> Color {
+    texture.filtered(hit.uv, &hit.point, &hit.footprint)
+}
+
+/// A metal, described by its complex index of refraction `eta + i k` per color channel,
+/// whose surface is made of GGX microfacets.
+#[derive(Debug, Clone, Copy)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl Conductor {
+    /// Conductor with the same perceptual `roughness`, in `[0, 1]`, in every direction.
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Self {
+        Conductor { eta, k, distribution: TrowbridgeReitz::from_roughness((roughness, roughness)) }
+    }
+
+    /// Gives different roughnesses along the surface's first and second tangent, which for
+    /// meshes and spheres follow increasing `u` and `v`.
+    pub fn anisotropic(self, roughness: (f64, f64)) -> Self {
+        Conductor { distribution: TrowbridgeReitz::from_roughness(roughness), ..self }
+    }
+
+    // Presets sampled from measured data at roughly 650, 550 and 450 nm.
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(Color::new(0.143, 0.374, 1.442), Color::new(3.983, 2.385, 1.603), roughness)
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(Color::new(0.200, 0.924, 1.102), Color::new(3.912, 2.452, 2.142), roughness)
+    }
+
+    pub fn aluminum(roughness: f64) -> Self {
+        Self::new(Color::new(1.657, 0.880, 0.521), Color::new(9.224, 6.270, 4.837), roughness)
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::new(Color::new(0.155, 0.117, 0.138), Color::new(4.828, 3.122, 2.147), roughness)
+    }
+}
+
+impl Bxdf for Conductor {
+    fn eval(&self, wo: &Vec3, wi: &Vec3) -> Color {
+        if self.is_delta() || wi.z <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let dist = &self.distribution;
+        let wm = (*wo + *wi).unit_vec();
+        let f = fresnel_conductor(wo.dot(&wm), self.eta, self.k);
+        f * (dist.d(&wm) * dist.g(wo, wi) / (4.0 * wo.z))
+    }
+
+    fn sample(&self, wo: &Vec3, _u_lobe: f64, u: (f64, f64)) -> Option<BsdfSample> {
+        let dist = &self.distribution;
+        if dist.is_smooth() {
+            let wi = Vec3::new(-wo.x, -wo.y, wo.z);
+            let weight = fresnel_conductor(wo.z, self.eta, self.k);
+            return Some(BsdfSample { wi, weight, pdf: 1.0, is_delta: true, eta: 1.0 });
+        }
+
+        let wm = dist.sample_wm(wo, u);
+        let wi = -wo.reflect(&wm);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        // The density of visible normals cancels all but the shadowing from this sample's weight.
+        let f = fresnel_conductor(wo.dot(&wm), self.eta, self.k);
+        let weight = f * (dist.g(wo, &wi) / dist.g1(wo));
+        Some(BsdfSample { wi, weight, pdf: dist.pdf_reflect(wo, &wi), is_delta: false, eta: 1.0 })
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        if self.is_delta() || wi.z <= 0.0 {
+            0.0
+        } else {
+            self.distribution.pdf_reflect(wo, wi)
+        }
+    }
+
+    fn is_delta(&self) -> bool {
+        self.distribution.is_smooth()
+    }
+}
+
+/// Glass, or any other transparent dielectric, with a surface of GGX microfacets that blur
+/// both what it reflects and what is seen through it.
+#[derive(Debug, Clone, Copy)]
+pub struct RoughDielectric {
+    /// Index of refraction inside over that outside, where the outside is the front.
+    pub ior: f64,
+    pub distribution: TrowbridgeReitz,
+}
+
+impl RoughDielectric {
+    pub fn new(ior: f64, roughness: f64) -> Self {
+        RoughDielectric { ior, distribution: TrowbridgeReitz::from_roughness((roughness, roughness)) }
+    }
+
+    pub fn anisotropic(self, roughness: (f64, f64)) -> Self {
+        RoughDielectric { distribution: TrowbridgeReitz::from_roughness(roughness), ..self }
+    }
+}
 
 #[derive(Clone)]
 pub enum Material {
-    Lambertian(Color),
-    Metal(Color, f64),
+    Lambertian(Arc<dyn Texture>),
+    /// Mirror reflection blurred by a random offset up to the given fuzz. Cheap, but not
+    /// energy conserving; `Conductor` is the physically based alternative.
+    Metal(Arc<dyn Texture>, f64),
+    Conductor(Conductor),
+    /// Perfectly smooth glass with the given index of refraction.
     Dielectric(f64),
+    RoughDielectric(RoughDielectric),
+    Principled(Principled),
+    /// Emits the given radiance from the front of the surface and scatters nothing.
+    DiffuseLight(Arc<dyn Texture>),
+    /// No surface at all, just the boundary of a `Volume`, which light passes straight
+    /// through.
+    Interface,
 }
 
 impl Material {
-    fn scatter_lambertian(albedo: Color, hit: Hit) -> Option<(Ray, Color)> {
-        let mut scatter_direction = hit.normal + with_rng(Vec3::random_unit_vec);
-        if scatter_direction.near_zero() {
-            scatter_direction = hit.normal;
-        }
-        let scattered = Ray::new(hit.point, scatter_direction);
-        Some((scattered, albedo))
+    /// How the surface scatters light at `hit`, or `None` if it doesn't.
+    pub fn bsdf(&self, hit: &Hit) -> Option<Bsdf> {
+        let bxdf: Box<dyn Bxdf> = match self {
+            Material::Lambertian(albedo) => Box::new(LambertianBxdf { albedo: lookup(albedo.as_ref(), hit) }),
+            Material::Metal(albedo, fuzz) => Box::new(FuzzyMirrorBxdf { albedo: lookup(albedo.as_ref(), hit), fuzz: *fuzz }),
+            Material::Conductor(conductor) => Box::new(*conductor),
+            Material::Dielectric(ior) => Box::new(DielectricBxdf {
+                eta: if hit.front_face { *ior } else { 1.0 / ior },
+                distribution: TrowbridgeReitz::new(0.0, 0.0),
+            }),
+            Material::RoughDielectric(dielectric) => Box::new(DielectricBxdf {
+                eta: if hit.front_face { dielectric.ior } else { 1.0 / dielectric.ior },
+                distribution: dielectric.distribution,
+            }),
+            Material::Principled(principled) => Box::new(principled.bxdf(hit)),
+            Material::DiffuseLight(_) | Material::Interface => return None,
+        };
+        Some(Bsdf::new(hit.shading_frame(), bxdf))
     }
 
-    fn scatter_metal(albedo: Color, fuzz: f64, hit: Hit, r: Ray) -> Option<(Ray, Color)> {
-        let reflected = r.d.unit_vec().reflect(&hit.normal);
-        let scattered = Ray::new(hit.point, reflected + fuzz * with_rng(Vec3::random_in_unit_sphere));
-        if scattered.d.dot(&hit.normal) > 0.0 {
-            Some((scattered, albedo))
-        } else {
-            None
+    /// The color the surface most obviously has at `hit`, before any lighting, for debug
+    /// views. For lights it is the color they emit.
+    pub fn albedo(&self, hit: &Hit) -> Color {
+        match self {
+            Material::Lambertian(texture) | Material::Metal(texture, _) | Material::DiffuseLight(texture) => lookup(texture.as_ref(), hit),
+            Material::Conductor(conductor) => fresnel_conductor(1.0, conductor.eta, conductor.k),
+            Material::Dielectric(_) | Material::RoughDielectric(_) => Color::new(1.0, 1.0, 1.0),
+            Material::Principled(principled) => lookup(principled.base_color.as_ref(), hit),
+            Material::Interface => Color::new(0.0, 0.0, 0.0),
         }
     }
 
-    fn scatter_dielectric(ir: f64, hit: Hit, r: Ray) -> Option<(Ray, Color)> {
-        let refraction_ratio = if hit.front_face { 1.0 / ir } else { ir };
-
-        let unit_direction = r.d.unit_vec();
-        let cos_theta = (-unit_direction).dot(&hit.normal).min(1.0);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    /// Tells materials apart in debug views. Textures count as the same if they're the same
+    /// object, so clones of a material share an ID, while parameters count if they're equal.
+    pub fn id(&self) -> u64 {
+        let texture = |t: &Arc<dyn Texture>| Arc::as_ptr(t) as *const () as usize;
+        let mut hasher = DefaultHasher::new();
+        mem::discriminant(self).hash(&mut hasher);
+        match self {
+            Material::Lambertian(t) | Material::DiffuseLight(t) => texture(t).hash(&mut hasher),
+            Material::Metal(t, fuzz) => (texture(t), fuzz.to_bits()).hash(&mut hasher),
+            Material::Conductor(c) => {
+                let (ax, ay) = c.distribution.alpha();
+                [c.eta.r, c.eta.g, c.eta.b, c.k.r, c.k.g, c.k.b, ax, ay].map(f64::to_bits).hash(&mut hasher);
+            }
+            Material::Dielectric(ior) => ior.to_bits().hash(&mut hasher),
+            Material::RoughDielectric(d) => {
+                let (ax, ay) = d.distribution.alpha();
+                [d.ior, ax, ay].map(f64::to_bits).hash(&mut hasher);
+            }
+            Material::Principled(p) => {
+                (texture(&p.base_color), texture(&p.metallic), texture(&p.roughness)).hash(&mut hasher);
+                for v in &[p.specular, p.specular_tint, p.anisotropic, p.sheen, p.sheen_tint, p.clearcoat, p.clearcoat_gloss, p.transmission, p.ior] {
+                    v.to_bits().hash(&mut hasher);
+                }
+            }
+            Material::Interface => {}
+        }
+        hasher.finish()
+    }
 
-        Some(if refraction_ratio * sin_theta > 1.0 || Self::reflectance(cos_theta, refraction_ratio) > with_rng(rand::Rng::gen) {
-            (Ray::new(hit.point, unit_direction.reflect(&hit.normal)), Color::BLACK)
-        } else {
-            (Ray::new(hit.point, unit_direction.refract(&hit.normal, refraction_ratio)), Color::BLACK)
-        })
+    pub fn is_interface(&self) -> bool {
+        matches!(self, Material::Interface)
     }
-    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
-        // Use Schlick's approximation for reflectance.
-        let r0_temp = (1.0 - ref_idx) / (1.0 + ref_idx);
-        let r0 = r0_temp * r0_temp;
-        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+
+    pub fn is_emissive(&self) -> bool {
+        matches!(self, Material::DiffuseLight(_))
     }
 
-    pub fn scatter(&self, hit: Hit, r: Ray) -> Option<(Ray, Color)> {
-        use self::Material::*;
+    pub fn emitted(&self, hit: &Hit) -> Color {
         match self {
-            Lambertian(color) => Self::scatter_lambertian(*color, hit),
-            Metal(color, fuzz) => Self::scatter_metal(*color, *fuzz, hit, r),
-            Dielectric(ir) => Self::scatter_dielectric(*ir, hit, r),
+            Material::DiffuseLight(emit) if hit.front_face => lookup(emit.as_ref(), hit),
+            _ => Color::new(0.0, 0.0, 0.0),
         }
     }
 }
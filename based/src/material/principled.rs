@@ -0,0 +1,362 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::bsdf::{BsdfSample, Bxdf};
+use crate::hittable::Hit;
+use crate::microfacet::{eval_dielectric, pdf_dielectric, sample_dielectric, TrowbridgeReitz};
+use crate::sampling::cosine_hemisphere;
+use crate::texture::Texture;
+use super::lookup;
+
+/// Below this roughness the specular lobes would be mirrors, which can't be mixed with the
+/// others by evaluating them, so roughness is clamped to it.
+const MIN_ROUGHNESS: f64 = 0.05;
+
+/// Disney's principled BSDF, one material covering most opaque and transparent surfaces
+/// with a handful of intuitive parameters in `[0, 1]`. Follows Burley's 2012 and 2015 course
+/// notes: a retro-reflective diffuse base with sheen, a GGX specular layer, a clearcoat
+/// above both, and a rough glass lobe for transmission.
+///
+/// `metallic` and `roughness` are read from the red channel of their textures.
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    /// Strength of the specular reflection of non-metals, where the default of 0.5 is a
+    /// reflectance of 4% at normal incidence.
+    pub specular: f64,
+    /// How much non-metal specular reflection takes on the base color.
+    pub specular_tint: f64,
+    /// Stretches highlights along the surface's first tangent.
+    pub anisotropic: f64,
+    /// Extra reflection at grazing angles, for cloth.
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    /// Strength of a second, colorless and thin specular layer.
+    pub clearcoat: f64,
+    /// Sharpness of the clearcoat, from satin at zero to gloss at one.
+    pub clearcoat_gloss: f64,
+    /// How much of the non-metal base is glass rather than diffuse.
+    pub transmission: f64,
+    /// Index of refraction of the glass, inside over outside.
+    pub ior: f64,
+}
+
+impl Principled {
+    /// A rough, white-speckled plastic of the given color.
+    pub fn new(base_color: Arc<dyn Texture>) -> Self {
+        Principled {
+            base_color,
+            metallic: Arc::new(Color::new(0.0, 0.0, 0.0)),
+            roughness: Arc::new(Color::new(0.5, 0.5, 0.5)),
+            specular: 0.5,
+            specular_tint: 0.0,
+            anisotropic: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+        }
+    }
+
+    pub fn metallic(self, metallic: Arc<dyn Texture>) -> Self {
+        Principled { metallic, ..self }
+    }
+
+    pub fn roughness(self, roughness: Arc<dyn Texture>) -> Self {
+        Principled { roughness, ..self }
+    }
+
+    pub fn specular(self, specular: f64) -> Self {
+        Principled { specular, ..self }
+    }
+
+    pub fn specular_tint(self, specular_tint: f64) -> Self {
+        Principled { specular_tint, ..self }
+    }
+
+    pub fn anisotropic(self, anisotropic: f64) -> Self {
+        Principled { anisotropic, ..self }
+    }
+
+    pub fn sheen(self, sheen: f64, sheen_tint: f64) -> Self {
+        Principled { sheen, sheen_tint, ..self }
+    }
+
+    pub fn clearcoat(self, clearcoat: f64, clearcoat_gloss: f64) -> Self {
+        Principled { clearcoat, clearcoat_gloss, ..self }
+    }
+
+    pub fn transmission(self, transmission: f64, ior: f64) -> Self {
+        Principled { transmission, ior, ..self }
+    }
+
+    /// The scattering at `hit`, with the textures looked up.
+    pub fn bxdf(&self, hit: &Hit) -> PrincipledBxdf {
+        self.bxdf_from(lookup(self.base_color.as_ref(), hit), lookup(self.metallic.as_ref(), hit).r, lookup(self.roughness.as_ref(), hit).r, hit.front_face)
+    }
+
+    fn bxdf_from(&self, color: Color, metallic: f64, roughness: f64, front_face: bool) -> PrincipledBxdf {
+        let metallic = metallic.clamp(0.0, 1.0);
+        let roughness = roughness.clamp(MIN_ROUGHNESS, 1.0);
+        let transmission = self.transmission.clamp(0.0, 1.0);
+
+        // The hue and saturation of the base color, without its brightness.
+        let luminance = color.luminance();
+        let tint = if luminance > 0.0 { color * (1.0 / luminance) } else { Color::new(1.0, 1.0, 1.0) };
+        let white = Color::new(1.0, 1.0, 1.0);
+        let dielectric_spec0 = lerp(white, tint, self.specular_tint) * (0.08 * self.specular);
+
+        let aspect = (1.0 - 0.9 * self.anisotropic.clamp(0.0, 1.0)).sqrt();
+        let alpha = roughness * roughness;
+
+        PrincipledBxdf {
+            color,
+            roughness,
+            spec0: lerp(dielectric_spec0, color, metallic),
+            sheen: lerp(white, tint, self.sheen_tint) * self.sheen,
+            distribution: TrowbridgeReitz::new(alpha / aspect, alpha * aspect),
+            clearcoat_alpha: 0.1 + (0.001 - 0.1) * self.clearcoat_gloss.clamp(0.0, 1.0),
+            eta: if front_face { self.ior } else { 1.0 / self.ior },
+            diffuse_weight: (1.0 - metallic) * (1.0 - transmission),
+            specular_weight: 1.0 - (1.0 - metallic) * transmission,
+            clearcoat_weight: 0.25 * self.clearcoat.max(0.0),
+            glass_weight: (1.0 - metallic) * transmission,
+        }
+    }
+}
+
+/// The parameters of a `Principled` resolved at one point.
+pub struct PrincipledBxdf {
+    color: Color,
+    roughness: f64,
+    /// Specular reflectance at normal incidence.
+    spec0: Color,
+    sheen: Color,
+    distribution: TrowbridgeReitz,
+    clearcoat_alpha: f64,
+    eta: f64,
+    diffuse_weight: f64,
+    specular_weight: f64,
+    clearcoat_weight: f64,
+    glass_weight: f64,
+}
+
+impl PrincipledBxdf {
+    /// How often each of the diffuse, specular, clearcoat and glass lobes is sampled, roughly
+    /// in proportion to how much light it reflects towards `wo`.
+    fn probabilities(&self, wo: &Vec3) -> [f64; 4] {
+        let fresnel = schlick_weight(wo.z);
+        let weights = [
+            self.diffuse_weight * self.color.luminance().max(0.01),
+            self.specular_weight * (self.spec0.luminance() + (1.0 - self.spec0.luminance()) * fresnel),
+            self.clearcoat_weight * (0.04 + 0.96 * fresnel),
+            self.glass_weight,
+        ];
+        let total: f64 = weights.iter().sum();
+        if total > 0.0 { weights.map(|w| w / total) } else { weights }
+    }
+}
+
+impl Bxdf for PrincipledBxdf {
+    fn sample(&self, wo: &Vec3, u_lobe: f64, u: (f64, f64)) -> Option<BsdfSample> {
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let probabilities = self.probabilities(wo);
+        let mut u_lobe = u_lobe;
+        let mut lobe = 0;
+        while lobe < 3 && u_lobe >= probabilities[lobe] {
+            u_lobe -= probabilities[lobe];
+            lobe += 1;
+        }
+
+        let wi = match lobe {
+            0 => cosine_hemisphere(u),
+            1 => -wo.reflect(&self.distribution.sample_wm(wo, u)),
+            2 => -wo.reflect(&sample_gtr1(self.clearcoat_alpha, u)),
+            // What's left of the lobe choice picks between reflection and transmission.
+            _ => sample_dielectric(&self.distribution, self.eta, wo, u, (u_lobe / probabilities[3]).min(1.0))?.0,
+        };
+        let pdf = self.pdf(wo, &wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        // Weighted by every lobe's density rather than just the sampled one's, which keeps
+        // the weight low when an unlikely lobe happens to be picked.
+        Some(BsdfSample { wi, weight: self.eval(wo, &wi) * (1.0 / pdf), pdf, is_delta: false, eta: if wi.z < 0.0 { self.eta } else { 1.0 } })
+    }
+
+    fn eval(&self, wo: &Vec3, wi: &Vec3) -> Color {
+        let black = Color::new(0.0, 0.0, 0.0);
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return black;
+        }
+        let glass = if self.glass_weight > 0.0 {
+            let f = self.glass_weight * eval_dielectric(&self.distribution, self.eta, wo, wi);
+            // Only what's seen through the glass takes on its color.
+            if wi.z < 0.0 { self.color * f } else { Color::new(f, f, f) }
+        } else {
+            black
+        };
+        if wi.z < 0.0 {
+            return glass;
+        }
+
+        let wh = (*wo + *wi).unit_vec();
+        let cos_d = wi.dot(&wh);
+        let (fl, fv, fh) = (schlick_weight(wi.z), schlick_weight(wo.z), schlick_weight(cos_d));
+
+        // Burley diffuse, with retro-reflection at grazing angles on rough surfaces.
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let fd = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv);
+        let diffuse = (self.color * (fd / PI) + self.sheen * fh) * (self.diffuse_weight * wi.z);
+
+        let dist = &self.distribution;
+        let fresnel = self.spec0 + (Color::new(1.0, 1.0, 1.0) - self.spec0) * fh;
+        let specular = fresnel * (self.specular_weight * dist.d(&wh) * dist.g(wo, wi) / (4.0 * wo.z));
+
+        let clearcoat = if self.clearcoat_weight > 0.0 {
+            // Burley's fixed, fairly rough shadowing, which already includes the 1 / 4 cos cos.
+            let g = smith_g_ggx(wo.z, 0.25) * smith_g_ggx(wi.z, 0.25);
+            let f = self.clearcoat_weight * gtr1(wh.z, self.clearcoat_alpha) * g * (0.04 + 0.96 * fh) * wi.z;
+            Color::new(f, f, f)
+        } else {
+            black
+        };
+
+        diffuse + specular + clearcoat + glass
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return 0.0;
+        }
+        let [diffuse, specular, clearcoat, glass] = self.probabilities(wo);
+        let mut pdf = 0.0;
+        if glass > 0.0 {
+            pdf += glass * pdf_dielectric(&self.distribution, self.eta, wo, wi);
+        }
+        if wi.z > 0.0 {
+            let wh = (*wo + *wi).unit_vec();
+            pdf += diffuse * wi.z / PI;
+            pdf += specular * self.distribution.pdf_reflect(wo, wi);
+            if clearcoat > 0.0 {
+                pdf += clearcoat * gtr1(wh.z, self.clearcoat_alpha) * wh.z / (4.0 * wo.dot(&wh));
+            }
+        }
+        pdf
+    }
+
+    fn is_delta(&self) -> bool {
+        // Roughness is clamped to keep every lobe rough enough to evaluate.
+        false
+    }
+}
+
+#[inline]
+fn lerp(a: Color, b: Color, t: f64) -> Color {
+    a * (1.0 - t) + b * t
+}
+
+/// The factor Schlick's approximation blends reflectance towards one by.
+#[inline]
+fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+/// Berry's distribution, the generalized Trowbridge-Reitz with an exponent of one, whose
+/// long tails give the clearcoat a haze around its highlights.
+fn gtr1(cos_h: f64, alpha: f64) -> f64 {
+    if cos_h <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+}
+
+/// Picks a normal with density `gtr1(cos_h) * cos_h`.
+fn sample_gtr1(alpha: f64, u: (f64, f64)) -> Vec3 {
+    let a2 = alpha * alpha;
+    let cos_h = ((1.0 - a2.powf(1.0 - u.0)) / (1.0 - a2)).max(0.0).sqrt();
+    let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(sin_h * phi.cos(), sin_h * phi.sin(), cos_h)
+}
+
+/// Separable Smith shadowing for GGX, divided by `2 cos_theta`.
+fn smith_g_ggx(cos_theta: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    let c2 = cos_theta * cos_theta;
+    1.0 / (cos_theta + (a2 + c2 - a2 * c2).sqrt())
+}
+
+#[cfg(test)]
+mod principled_tests {
+    use super::*;
+    use crate::sampling::spherical;
+    use rand_xoshiro::Xoshiro256PlusPlus;
+    use rand::{Rng, SeedableRng};
+
+    /// Integrates `f` over the whole sphere of directions.
+    fn integrate(f: impl Fn(&Vec3) -> f64) -> f64 {
+        let n = 400;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..2 * n {
+                let theta = (i as f64 + 0.5) / n as f64 * PI;
+                let phi = (j as f64 + 0.5) / n as f64 * PI;
+                sum += f(&spherical(theta, phi)) * theta.sin() * (PI / n as f64) * (PI / n as f64);
+            }
+        }
+        sum
+    }
+
+    fn examples() -> Vec<PrincipledBxdf> {
+        let color = Color::new(0.8, 0.4, 0.2);
+        let base = Principled::new(Arc::new(color));
+        vec![
+            base.clone().bxdf_from(color, 0.0, 0.5, true),
+            base.clone().bxdf_from(color, 1.0, 0.3, true),
+            base.clone().sheen(1.0, 0.5).clearcoat(1.0, 0.5).anisotropic(0.8).bxdf_from(color, 0.3, 0.6, true),
+            base.clone().transmission(0.9, 1.5).bxdf_from(color, 0.0, 0.4, true),
+            base.transmission(1.0, 1.5).bxdf_from(color, 0.0, 0.4, false),
+        ]
+    }
+
+    #[test]
+    fn test_pdf_normalized() {
+        // Densities add up to at most one, less only where sampled directions are discarded.
+        let wo = spherical(0.8, 0.3);
+        for bxdf in examples() {
+            let total = integrate(|wi| bxdf.pdf(&wo, wi));
+            assert!(total > 0.9 && total < 1.01, "{}", total);
+        }
+    }
+
+    #[test]
+    fn test_sample_matches_eval() {
+        // Reflected and transmitted light, once by integrating and once by sampling, which
+        // only agree if the samples follow `pdf`.
+        let wo = spherical(0.8, 0.3);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(9);
+        for bxdf in examples() {
+            let integral = integrate(|wi| bxdf.eval(&wo, wi).luminance());
+            let samples = 100_000;
+            let mut estimate = 0.0;
+            for _ in 0..samples {
+                if let Some(sample) = bxdf.sample(&wo, rng.gen(), (rng.gen(), rng.gen())) {
+                    estimate += sample.weight.luminance();
+                }
+            }
+            estimate /= samples as f64;
+            assert!((estimate - integral).abs() < 0.02 * integral.max(0.1), "{} {}", estimate, integral);
+            // Roughly energy conserving.
+            assert!(integral < 1.1, "{}", integral);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::f64::consts::PI;
+use glitz::frame::Frame;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::ray::Ray;
+
+pub mod homogeneous;
+pub mod grid;
+
+pub use self::homogeneous::Homogeneous;
+pub use self::grid::{DensityGrid, GridMedium};
+
+/// Something filling space that absorbs and scatters light travelling through it, like fog,
+/// smoke or milk.
+pub trait Medium: Send + Sync {
+    /// How light scattering inside the medium changes direction.
+    fn phase(&self) -> HenyeyGreenstein;
+
+    /// Picks where along `r`, up to `tmax`, light reaching the ray's origin last scattered,
+    /// if it did before `tmax` at all.
+    fn sample(&self, r: &Ray, tmax: f64) -> MediumSample;
+
+    /// Fraction of light that gets from `tmax` along `r` back to its origin without being
+    /// absorbed or scattered away.
+    fn transmittance(&self, r: &Ray, tmax: f64) -> Color;
+}
+
+/// Where `Medium::sample` found light scattered along a ray.
+#[derive(Debug, Clone, Copy)]
+pub struct MediumSample {
+    /// How far along the ray, or `None` if it passed through to the end unscattered.
+    pub t: Option<f64>,
+    /// Transmittance up to there, times the scattering coefficient if it scattered, divided
+    /// by the density of the sample.
+    pub weight: Color,
+}
+
+/// The Henyey-Greenstein phase function, which scatters light mostly forwards for positive
+/// `g`, mostly backwards for negative `g`, and evenly when it's zero. `g` is the average
+/// cosine of the angle turned.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct HenyeyGreenstein {
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> Self {
+        HenyeyGreenstein { g: g.clamp(-0.99, 0.99) }
+    }
+
+    /// Density of light arriving along `-wi` leaving along `wo`, both unit vectors pointing
+    /// away from the point of scattering. Also the density with which `sample` returns `wi`.
+    pub fn eval(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        let g = self.g;
+        let cos = -wo.dot(wi);
+        let denom = 1.0 + g * g - 2.0 * g * cos;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    /// Samples the direction light scattered towards `wo` came from, along with its density.
+    /// The phase function is sampled exactly, so the two cancel out.
+    pub fn sample(&self, wo: &Vec3, u: (f64, f64)) -> (Vec3, f64) {
+        let g = self.g;
+        // Cosine of the angle between the directions light travels before and after.
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u.0
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u.0);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        let wi = -Frame::from_normal(*wo).to_world(&Vec3::new(sin * phi.cos(), sin * phi.sin(), cos));
+        (wi, self.eval(wo, &wi))
+    }
+}
+
+/// Per channel `exp(-sigma_t * dist)`, where channels that don't attenuate at all stay at one
+/// even over infinite distances.
+fn beer_lambert(sigma_t: &Color, dist: f64) -> Color {
+    let channel = |sigma: f64| if sigma == 0.0 { 1.0 } else { (-sigma * dist).exp() };
+    Color::new(channel(sigma_t.r), channel(sigma_t.g), channel(sigma_t.b))
+}
+
+#[cfg(test)]
+mod medium_tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+    use crate::sampling::uniform_sphere;
+
+    #[test]
+    fn test_henyey_greenstein() {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(5);
+        let wo = Vec3::new(0.3, -0.4, 0.5).unit_vec();
+        for &g in &[0.0, 0.7, -0.4] {
+            let phase = HenyeyGreenstein::new(g);
+            // Integrates to one over the sphere, and has mean cosine g.
+            let n = 200_000;
+            let total = (0..n).map(|_| phase.eval(&wo, &uniform_sphere(rng.gen())) * 4.0 * PI).sum::<f64>() / n as f64;
+            assert!((total - 1.0).abs() < 0.02, "{} {}", g, total);
+            let mean_cos = (0..n).map(|_| {
+                let (wi, pdf) = phase.sample(&wo, rng.gen());
+                assert!((pdf - phase.eval(&wo, &wi)).abs() < 1e-12 && (wi.length() - 1.0).abs() < 1e-9);
+                -wo.dot(&wi)
+            }).sum::<f64>() / n as f64;
+            assert!((mean_cos - g).abs() < 0.01, "{} {}", g, mean_cos);
+        }
+    }
+}
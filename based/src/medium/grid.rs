@@ -0,0 +1,220 @@
+use glitz::aabb::Aabb;
+use glitz::vec::Vec3;
+use rand::Rng;
+use xenon::color::Color;
+use crate::medium::{HenyeyGreenstein, Medium, MediumSample};
+use crate::random::with_rng;
+use crate::ray::Ray;
+
+/// A dense 3D grid of densities, stored with x varying fastest, then y, then z. Values sit
+/// at the centers of the cells of the unit cube and are interpolated trilinearly between.
+#[derive(Clone)]
+pub struct DensityGrid {
+    resolution: [usize; 3],
+    values: Vec<f32>,
+    max: f64,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> Self {
+        assert!(resolution.iter().all(|&n| n > 0), "need at least one cell along each axis");
+        assert_eq!(values.len(), resolution.iter().product::<usize>(), "need exactly one value per cell");
+        let max = values.iter().fold(0.0f32, |m, &v| m.max(v)) as f64;
+        DensityGrid { resolution, values, max }
+    }
+
+    /// Grid filled with `density` evaluated at the center of each cell of the unit cube.
+    pub fn from_fn(resolution: [usize; 3], density: impl Fn(Vec3) -> f64) -> Self {
+        let [nx, ny, nz] = resolution;
+        let center = |i: usize, n: usize| (i as f64 + 0.5) / n as f64;
+        let values = (0..nz).flat_map(|z| (0..ny).flat_map(move |y| (0..nx).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| density(Vec3::new(center(x, nx), center(y, ny), center(z, nz))) as f32)
+            .collect();
+        DensityGrid::new(resolution, values)
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    /// The largest density anywhere, which bounds what `lookup` returns.
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Value of the cell at `(x, y, z)`, with coordinates clamped to the grid.
+    pub fn get(&self, x: i64, y: i64, z: i64) -> f64 {
+        let [nx, ny, nz] = self.resolution;
+        let clamp = |i: i64, n: usize| i.clamp(0, n as i64 - 1) as usize;
+        self.values[(clamp(z, nz) * ny + clamp(y, ny)) * nx + clamp(x, nx)] as f64
+    }
+
+    /// Density at `p` in the unit cube, interpolated trilinearly, and zero outside it.
+    pub fn lookup(&self, p: &Vec3) -> f64 {
+        if !(0.0..=1.0).contains(&p.x) || !(0.0..=1.0).contains(&p.y) || !(0.0..=1.0).contains(&p.z) {
+            return 0.0;
+        }
+        let [nx, ny, nz] = self.resolution;
+        let (x, y, z) = (p.x * nx as f64 - 0.5, p.y * ny as f64 - 0.5, p.z * nz as f64 - 0.5);
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (dx, dy, dz) = (x - x0, y - y0, z - z0);
+        let (x0, y0, z0) = (x0 as i64, y0 as i64, z0 as i64);
+
+        let lerp = |t: f64, a: f64, b: f64| a + t * (b - a);
+        let along_x = |y: i64, z: i64| lerp(dx, self.get(x0, y, z), self.get(x0 + 1, y, z));
+        lerp(dz,
+            lerp(dy, along_x(y0, z0), along_x(y0 + 1, z0)),
+            lerp(dy, along_x(y0, z0 + 1), along_x(y0 + 1, z0 + 1)),
+        )
+    }
+}
+
+/// A medium whose density varies through space as given by a `DensityGrid`, like a puff of
+/// simulated smoke. Distances are sampled by delta tracking and transmittance estimated by
+/// ratio tracking, both against the largest density in the grid.
+pub struct GridMedium {
+    grid: DensityGrid,
+    bounds: Aabb,
+    sigma_t: f64,
+    albedo: Color,
+    phase: HenyeyGreenstein,
+}
+
+impl GridMedium {
+    /// The grid stretched over `bounds` in world space, attenuating the fraction `sigma_t`
+    /// of light per unit distance where the density is one, of which `albedo` is scattered
+    /// rather than absorbed. Outside `bounds` it's empty.
+    pub fn new(grid: DensityGrid, bounds: Aabb, sigma_t: f64, albedo: Color) -> Self {
+        GridMedium { grid, bounds, sigma_t, albedo, phase: HenyeyGreenstein::new(0.0) }
+    }
+
+    /// Mean cosine of the angle light turns when scattering, see `HenyeyGreenstein`.
+    pub fn anisotropy(self, g: f64) -> Self {
+        GridMedium { phase: HenyeyGreenstein::new(g), ..self }
+    }
+
+    fn density(&self, p: &Vec3) -> f64 {
+        self.grid.lookup(&self.bounds.offset(p))
+    }
+
+    // Part of `r` in `[0, tmax]` that's inside the bounds, if any.
+    fn clip(&self, r: &Ray, tmax: f64) -> Option<(f64, f64)> {
+        let (mut t0, mut t1) = (0.0, tmax);
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.d[axis];
+            let mut near = (self.bounds.min[axis] - r.o[axis]) * inv_d;
+            let mut far = (self.bounds.max[axis] - r.o[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // Written so that NaNs from 0 * inf leave the interval unchanged.
+            t0 = if near > t0 { near } else { t0 };
+            t1 = if far < t1 { far } else { t1 };
+            if t1 <= t0 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+
+    /// Steps along `r` from `t0` towards `t1` with distances sampled as if the whole grid had
+    /// its largest density, calling `step` at each point until it returns `false`. Returns
+    /// the last point reached, or `None` if it got past `t1`.
+    fn track(&self, r: &Ray, (t0, t1): (f64, f64), mut step: impl FnMut(f64, f64) -> bool) -> Option<f64> {
+        let majorant = self.sigma_t * self.grid.max() * r.d.length();
+        if majorant <= 0.0 {
+            return None;
+        }
+        let mut t = t0;
+        loop {
+            let (u, u_step) = with_rng(|rng| (rng.gen::<f64>(), rng.gen::<f64>()));
+            t -= (1.0 - u).ln() / majorant;
+            if t >= t1 {
+                return None;
+            }
+            // Fraction of the majorant that is real collisions rather than null ones.
+            let real = self.sigma_t * self.density(&r.at(t)) * r.d.length() / majorant;
+            if !step(real, u_step) {
+                return Some(t);
+            }
+        }
+    }
+}
+
+impl Medium for GridMedium {
+    fn phase(&self) -> HenyeyGreenstein {
+        self.phase
+    }
+
+    fn sample(&self, r: &Ray, tmax: f64) -> MediumSample {
+        let pass = MediumSample { t: None, weight: Color::new(1.0, 1.0, 1.0) };
+        let segment = match self.clip(r, tmax) {
+            Some(segment) => segment,
+            None => return pass,
+        };
+        // Delta tracking: stop at a real collision, which scatters rather than absorbs with
+        // probability `albedo`, left to the weight.
+        match self.track(r, segment, |real, u| u >= real) {
+            Some(t) => MediumSample { t: Some(t), weight: self.albedo },
+            None => pass,
+        }
+    }
+
+    fn transmittance(&self, r: &Ray, tmax: f64) -> Color {
+        let segment = match self.clip(r, tmax) {
+            Some(segment) => segment,
+            None => return Color::new(1.0, 1.0, 1.0),
+        };
+        // Ratio tracking: the chance of getting past every collision along the way.
+        let mut tr = 1.0;
+        self.track(r, segment, |real, _| {
+            tr *= 1.0 - real;
+            tr > 0.0
+        });
+        Color::new(tr, tr, tr)
+    }
+}
+
+#[cfg(test)]
+mod grid_tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        // Trilinear interpolation reproduces linear functions between cell centers.
+        let linear = |p: Vec3| 1.0 + p.x + 2.0 * p.y - 0.5 * p.z;
+        let grid = DensityGrid::from_fn([4, 5, 6], linear);
+        for &p in &[Vec3::new(0.3, 0.5, 0.7), Vec3::new(0.125, 0.1, 0.5), Vec3::new(0.8, 0.85, 0.2)] {
+            assert!((grid.lookup(&p) - linear(p)).abs() < 1e-6, "{:?}", p);
+        }
+        assert_eq!(grid.lookup(&Vec3::new(0.5, 1.5, 0.5)), 0.0);
+        assert!((grid.max() - linear(Vec3::new(0.875, 0.9, 1.0 / 12.0))).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_tracking() {
+        // Half the grid at density one and half empty, so the tracking estimates should match
+        // a homogeneous medium over the half that's filled, counting the ramp between the two
+        // middle cells as half full.
+        let grid = DensityGrid::from_fn([8, 8, 8], |p| if p.x < 0.5 { 1.0 } else { 0.0 });
+        let bounds = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 2.0, 2.0));
+        let medium = GridMedium::new(grid, bounds, 0.8, Color::new(0.5, 0.5, 0.5));
+        // Runs along x through the middle of cells, from outside the bounds.
+        let r = Ray::new(Vec3::new(-1.0, 0.9, 1.1), Vec3::new(2.0, 0.0, 0.0));
+        let expected = (-0.8f64).exp();
+
+        let n = 100_000;
+        let tr = (0..n).map(|_| medium.transmittance(&r, 10.0).g).sum::<f64>() / n as f64;
+        assert!((tr - expected).abs() < 0.01, "{} {}", tr, expected);
+        let mut passed = 0;
+        for _ in 0..n {
+            let sample = medium.sample(&r, 10.0);
+            match sample.t {
+                Some(t) => assert!(sample.weight.g == 0.5 && r.at(t).x > 0.0 && r.at(t).x < 1.125),
+                None => passed += 1,
+            }
+        }
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.01, "{} {}", passed, expected);
+        assert_eq!(medium.transmittance(&Ray::new(Vec3::new(-1.0, 3.0, 1.0), Vec3::new(1.0, 0.0, 0.0)), 10.0).g, 1.0);
+    }
+}
@@ -0,0 +1,99 @@
+use rand::Rng;
+use xenon::color::Color;
+use crate::medium::{beer_lambert, HenyeyGreenstein, Medium, MediumSample};
+use crate::random::with_rng;
+use crate::ray::Ray;
+
+/// A medium of the same density everywhere, whose distances to scatter at can be sampled in
+/// closed form.
+pub struct Homogeneous {
+    sigma_a: Color,
+    sigma_s: Color,
+    phase: HenyeyGreenstein,
+}
+
+impl Homogeneous {
+    /// Medium absorbing the fraction `sigma_a` and scattering the fraction `sigma_s` of the
+    /// light passing through each unit of distance, per channel. It scatters evenly in
+    /// every direction unless given an `anisotropy`.
+    pub fn new(sigma_a: Color, sigma_s: Color) -> Self {
+        Homogeneous { sigma_a, sigma_s, phase: HenyeyGreenstein::new(0.0) }
+    }
+
+    /// Mean cosine of the angle light turns when scattering, see `HenyeyGreenstein`.
+    pub fn anisotropy(self, g: f64) -> Self {
+        Homogeneous { phase: HenyeyGreenstein::new(g), ..self }
+    }
+
+    fn sigma_t(&self) -> Color {
+        self.sigma_a + self.sigma_s
+    }
+}
+
+impl Medium for Homogeneous {
+    fn phase(&self) -> HenyeyGreenstein {
+        self.phase
+    }
+
+    fn sample(&self, r: &Ray, tmax: f64) -> MediumSample {
+        // Free-flight sampling of the distance in one channel, picked at random.
+        let sigma_t = self.sigma_t();
+        let (u_channel, u) = with_rng(|rng| (rng.gen::<f64>(), rng.gen::<f64>()));
+        let channel = [sigma_t.r, sigma_t.g, sigma_t.b][((u_channel * 3.0) as usize).min(2)];
+        let length = r.d.length();
+        let dist = -(1.0 - u).ln() / channel;
+        let scattered = dist < tmax * length;
+        let dist = if scattered { dist } else { tmax * length };
+
+        // The density of the sample is averaged over the channels that might have made it.
+        let tr = beer_lambert(&sigma_t, dist);
+        let density = if scattered { sigma_t * tr } else { tr };
+        let pdf = (density.r + density.g + density.b) / 3.0;
+        if pdf == 0.0 {
+            return MediumSample { t: None, weight: Color::new(0.0, 0.0, 0.0) };
+        }
+        if scattered {
+            MediumSample { t: Some(dist / length), weight: tr * self.sigma_s * (1.0 / pdf) }
+        } else {
+            MediumSample { t: None, weight: tr * (1.0 / pdf) }
+        }
+    }
+
+    fn transmittance(&self, r: &Ray, tmax: f64) -> Color {
+        beer_lambert(&self.sigma_t(), tmax * r.d.length())
+    }
+}
+
+#[cfg(test)]
+mod homogeneous_tests {
+    use super::*;
+    use glitz::vec::Vec3;
+
+    #[test]
+    fn test_sample() {
+        // The pass-through weights average out to the transmittance, and the scattering
+        // weights to the integral of transmittance times sigma_s along the way.
+        let medium = Homogeneous::new(Color::new(0.1, 0.5, 0.0), Color::new(0.3, 0.5, 0.2));
+        let r = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 2.0));
+        let n = 200_000;
+        let (mut passed, mut scattered) = (Color::new(0.0, 0.0, 0.0), Color::new(0.0, 0.0, 0.0));
+        for _ in 0..n {
+            let sample = medium.sample(&r, 1.5);
+            match sample.t {
+                Some(t) => {
+                    assert!((0.0..1.5).contains(&t));
+                    scattered += sample.weight;
+                }
+                None => passed += sample.weight,
+            }
+        }
+        let expected_passed = medium.transmittance(&r, 1.5);
+        let sigma_t = medium.sigma_t();
+        let integral = |s: f64, t: f64| if t == 0.0 { 0.0 } else { s / t * (1.0 - (-t * 3.0).exp()) };
+        let expected_scattered = Color::new(integral(0.3, sigma_t.r), integral(0.5, sigma_t.g), integral(0.2, sigma_t.b));
+        for (got, expected) in [(passed, expected_passed), (scattered, expected_scattered)] {
+            let got = got * (1.0 / n as f64);
+            assert!((got.r - expected.r).abs() < 0.01 && (got.g - expected.g).abs() < 0.01 && (got.b - expected.b).abs() < 0.01, "{:?} {:?}", got, expected);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use std::f64::consts::PI;
+use glitz::vec::Vec3;
+use xenon::color::Color;
+
+/// The GGX, or Trowbridge-Reitz, distribution of microfacet normals, with separate widths
+/// along the two tangents for anisotropic surfaces. Directions are in the local shading
+/// frame, with the macro surface normal along z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrowbridgeReitz {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        TrowbridgeReitz { alpha_x: alpha_x.max(1e-4), alpha_y: alpha_y.max(1e-4) }
+    }
+
+    /// Distribution for perceptual roughness in `[0, 1]` along each tangent, squared to get
+    /// the width so that roughness looks roughly linear.
+    pub fn from_roughness(roughness: (f64, f64)) -> Self {
+        Self::new(roughness.0 * roughness.0, roughness.1 * roughness.1)
+    }
+
+    /// Widths along the first and second tangent.
+    pub fn alpha(&self) -> (f64, f64) {
+        (self.alpha_x, self.alpha_y)
+    }
+
+    /// So narrow that it's better treated as a perfect mirror.
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    /// Density of microfacets with normal `wm`, per unit of projected macro surface area.
+    pub fn d(&self, wm: &Vec3) -> f64 {
+        if wm.z <= 0.0 {
+            return 0.0;
+        }
+        let e = (wm.x / self.alpha_x).powi(2) + (wm.y / self.alpha_y).powi(2) + wm.z * wm.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    /// Smith's auxiliary function, the ratio of hidden to visible microfacet area seen from `w`.
+    pub fn lambda(&self, w: &Vec3) -> f64 {
+        if w.z == 0.0 {
+            return f64::INFINITY;
+        }
+        let alpha2_tan2 = ((self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2)) / (w.z * w.z);
+        ((1.0 + alpha2_tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of microfacets visible from `w`.
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both `wo` and `wi`, with height correlation.
+    pub fn g(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of microfacet normals as seen from `w`, that is weighted by how much of each
+    /// faces `w`.
+    pub fn d_visible(&self, w: &Vec3, wm: &Vec3) -> f64 {
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).abs()
+    }
+
+    /// Picks a microfacet normal visible from `w` in proportion to `d_visible`, with Heitz's
+    /// 2018 method of sampling the projection of a hemisphere after stretching to unit roughness.
+    pub fn sample_wm(&self, w: &Vec3, u: (f64, f64)) -> Vec3 {
+        let mut wh = Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).unit_vec();
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+
+        let len2 = wh.x * wh.x + wh.y * wh.y;
+        let t1 = if len2 > 0.0 { Vec3::new(-wh.y, wh.x, 0.0) / len2.sqrt() } else { Vec3::new(1.0, 0.0, 0.0) };
+        let t2 = wh.cross(&t1);
+
+        // Uniform point on a disk, squashed onto the part of it the visible hemisphere covers.
+        let r = u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + wh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * wh;
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unit_vec()
+    }
+
+    /// Density of `sample_wm` producing `wi` by reflecting `wo`, per solid angle around `wi`.
+    pub fn pdf_reflect(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        let wm = (*wo + *wi).unit_vec();
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+        self.d_visible(wo, &wm) / (4.0 * wo.dot(&wm).abs())
+    }
+
+    /// Density of `sample_wm` producing `wi` by refracting `wo`, per solid angle around `wi`,
+    /// where `eta` is the index of refraction on the side of `wi` over that on the side of `wo`.
+    pub fn pdf_refract(&self, wo: &Vec3, wi: &Vec3, eta: f64) -> f64 {
+        match refraction_half_vector(wo, wi, eta) {
+            Some(wm) => {
+                let denom = (wi.dot(&wm) + wo.dot(&wm) / eta).powi(2);
+                self.d_visible(wo, &wm) * wi.dot(&wm).abs() / denom
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// Picks a direction scattered by a rough dielectric boundary from `wo`, in the local frame
+/// with `wo` above, where `eta` is the index of refraction below over that above. Chooses
+/// between reflection and transmission by their Fresnel weights. Returns the direction with
+/// its throughput weight, `eval` over `pdf`, which the Fresnel weights and visible normal
+/// densities mostly cancel out of, and its density. For smooth boundaries the density is
+/// just the chance of having picked reflection or transmission.
+pub fn sample_dielectric(dist: &TrowbridgeReitz, eta: f64, wo: &Vec3, u: (f64, f64), u_lobe: f64) -> Option<(Vec3, f64, f64)> {
+    let smooth = dist.is_smooth();
+    let wm = if smooth { Vec3::new(0.0, 0.0, 1.0) } else { dist.sample_wm(wo, u) };
+    let reflectance = fresnel_dielectric(wo.dot(&wm), eta);
+    let (wi, weight, chance) = if u_lobe < reflectance {
+        let wi = -wo.reflect(&wm);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        (wi, 1.0, reflectance)
+    } else {
+        let wi = refract(wo, &wm, eta)?;
+        if wi.z >= 0.0 {
+            return None;
+        }
+        // Radiance is squeezed into a smaller solid angle on the denser side.
+        (wi, 1.0 / (eta * eta), 1.0 - reflectance)
+    };
+    if smooth {
+        Some((wi, weight, chance))
+    } else {
+        Some((wi, weight * dist.g(wo, &wi) / dist.g1(wo), pdf_dielectric(dist, eta, wo, &wi)))
+    }
+}
+
+/// Light a rough dielectric boundary scatters from `wi` to `wo`, cosine included, in the
+/// local frame with `wo` above. Zero for smooth boundaries.
+pub fn eval_dielectric(dist: &TrowbridgeReitz, eta: f64, wo: &Vec3, wi: &Vec3) -> f64 {
+    if dist.is_smooth() || wo.z <= 0.0 || wi.z == 0.0 {
+        0.0
+    } else if wi.z > 0.0 {
+        let wm = (*wo + *wi).unit_vec();
+        dist.d(&wm) * dist.g(wo, wi) * fresnel_dielectric(wo.dot(&wm), eta) / (4.0 * wo.z)
+    } else {
+        match refraction_half_vector(wo, wi, eta) {
+            Some(wm) => {
+                let transmittance = 1.0 - fresnel_dielectric(wo.dot(&wm), eta);
+                let denom = (wi.dot(&wm) + wo.dot(&wm) / eta).powi(2) * wo.z;
+                dist.d(&wm) * dist.g(wo, wi) * transmittance * (wi.dot(&wm) * wo.dot(&wm) / denom).abs() / (eta * eta)
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// Solid angle density with which `sample_dielectric` picks `wi`. Zero for smooth boundaries.
+pub fn pdf_dielectric(dist: &TrowbridgeReitz, eta: f64, wo: &Vec3, wi: &Vec3) -> f64 {
+    if dist.is_smooth() || wo.z <= 0.0 || wi.z == 0.0 {
+        0.0
+    } else if wi.z > 0.0 {
+        let wm = (*wo + *wi).unit_vec();
+        fresnel_dielectric(wo.dot(&wm), eta) * dist.pdf_reflect(wo, wi)
+    } else {
+        match refraction_half_vector(wo, wi, eta) {
+            Some(wm) => (1.0 - fresnel_dielectric(wo.dot(&wm), eta)) * dist.pdf_refract(wo, wi, eta),
+            None => 0.0,
+        }
+    }
+}
+
+/// The microfacet normal that refracts `wo` into `wi`, facing up, or `None` if there isn't
+/// one that both directions see the front of.
+pub fn refraction_half_vector(wo: &Vec3, wi: &Vec3, eta: f64) -> Option<Vec3> {
+    let wm = eta * *wi + *wo;
+    if wm.dot(&wm) == 0.0 {
+        return None;
+    }
+    let wm = wm.unit_vec();
+    let wm = if wm.z < 0.0 { -wm } else { wm };
+    if wm.dot(wi) * wi.z < 0.0 || wm.dot(wo) * wo.z < 0.0 {
+        None
+    } else {
+        Some(wm)
+    }
+}
+
+/// Refracts `wo` through a surface with normal `n` on its side, given the index of
+/// refraction on the far side over that on `wo`'s. `None` on total internal reflection.
+pub fn refract(wo: &Vec3, n: &Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = n.dot(wo);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*wo / eta + (cos_i / eta - cos_t) * *n)
+}
+
+/// Fraction of unpolarized light reflected at a smooth boundary between dielectrics, from the
+/// cosine of the incident angle and the index of refraction on the far side over that on the
+/// near side. Negative cosines mean arriving from the far side.
+pub fn fresnel_dielectric(cos_theta: f64, eta: f64) -> f64 {
+    let (cos_i, eta) = if cos_theta < 0.0 { (-cos_theta, 1.0 / eta) } else { (cos_theta, eta) };
+    let cos_i = cos_i.min(1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// Fraction of light reflected off a conductor with complex index of refraction `eta + i k`
+/// relative to the outside, per color channel, from the cosine of the incident angle.
+pub fn fresnel_conductor(cos_theta: f64, eta: Color, k: Color) -> Color {
+    let f = |eta: f64, k: f64| {
+        let cos2 = cos_theta.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_theta.clamp(0.0, 1.0) * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rp + rs)
+    };
+    Color::new(f(eta.r, k.r), f(eta.g, k.g), f(eta.b, k.b))
+}
+
+#[cfg(test)]
+mod microfacet_tests {
+    use super::*;
+    use crate::sampling::spherical;
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+
+    #[test]
+    fn test_d_normalized() {
+        // Projected microfacet area adds up to the macro surface's.
+        let dist = TrowbridgeReitz::new(0.3, 0.6);
+        let n = 400;
+        let mut sum = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let theta = (i as f64 + 0.5) / n as f64 * PI / 2.0;
+                let phi = (j as f64 + 0.5) / n as f64 * 2.0 * PI;
+                let wm = spherical(theta, phi);
+                sum += dist.d(&wm) * wm.z * theta.sin() * (PI / 2.0 / n as f64) * (2.0 * PI / n as f64);
+            }
+        }
+        assert!((sum - 1.0).abs() < 0.01, "{}", sum);
+    }
+
+    #[test]
+    fn test_sample_wm_matches_pdf() {
+        // Reflected light off a white surface, once by integrating over the hemisphere and
+        // once by sampling visible normals, which only agree if the samples follow `pdf_reflect`.
+        let dist = TrowbridgeReitz::new(0.5, 0.2);
+        let wo = spherical(1.0, 0.4);
+        let f = |wi: &Vec3| {
+            let wm = (wo + *wi).unit_vec();
+            dist.d(&wm) * dist.g(&wo, wi) / (4.0 * wo.z * wi.z)
+        };
+
+        let n = 500;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let theta = (i as f64 + 0.5) / n as f64 * PI / 2.0;
+                let phi = (j as f64 + 0.5) / n as f64 * 2.0 * PI;
+                let wi = spherical(theta, phi);
+                integral += f(&wi) * wi.z * theta.sin() * (PI / 2.0 / n as f64) * (2.0 * PI / n as f64);
+            }
+        }
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(5);
+        let samples = 200_000;
+        let mut estimate = 0.0;
+        for _ in 0..samples {
+            let wm = dist.sample_wm(&wo, (rng.gen(), rng.gen()));
+            assert!(wm.z > 0.0);
+            let wi = -wo.reflect(&wm);
+            if wi.z > 0.0 {
+                estimate += f(&wi) * wi.z / dist.pdf_reflect(&wo, &wi);
+            }
+        }
+        estimate /= samples as f64;
+        assert!((estimate - integral).abs() < 0.01, "{} {}", estimate, integral);
+    }
+
+    #[test]
+    fn test_pdf_refract() {
+        // As above, for light transmitted into glass, ignoring Fresnel.
+        let dist = TrowbridgeReitz::new(0.4, 0.4);
+        let eta = 1.5;
+        let wo = spherical(0.7, 2.0);
+        let f = |wi: &Vec3| match refraction_half_vector(&wo, wi, eta) {
+            Some(wm) => {
+                let denom = (wi.dot(&wm) + wo.dot(&wm) / eta).powi(2);
+                dist.d(&wm) * dist.g(&wo, wi) * (wi.dot(&wm) * wo.dot(&wm) / (denom * wi.z * wo.z)).abs()
+            }
+            None => 0.0,
+        };
+
+        let n = 500;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let theta = PI - (i as f64 + 0.5) / n as f64 * PI / 2.0;
+                let phi = (j as f64 + 0.5) / n as f64 * 2.0 * PI;
+                let wi = spherical(theta, phi);
+                integral += f(&wi) * wi.z.abs() * theta.sin() * (PI / 2.0 / n as f64) * (2.0 * PI / n as f64);
+            }
+        }
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(6);
+        let samples = 200_000;
+        let mut estimate = 0.0;
+        for _ in 0..samples {
+            let wm = dist.sample_wm(&wo, (rng.gen(), rng.gen()));
+            if let Some(wi) = refract(&wo, &wm, eta) {
+                if wi.z < 0.0 {
+                    estimate += f(&wi) * wi.z.abs() / dist.pdf_refract(&wo, &wi, eta);
+                }
+            }
+        }
+        estimate /= samples as f64;
+        assert!((estimate - integral).abs() < 0.01, "{} {}", estimate, integral);
+    }
+
+    #[test]
+    fn test_sample_dielectric() {
+        // Sample weights agree with evaluating over the density, from both sides.
+        let dist = TrowbridgeReitz::new(0.3, 0.3);
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(7);
+        for &eta in &[1.5, 1.0 / 1.5] {
+            let wo = spherical(0.6, 1.0);
+            for _ in 0..1000 {
+                if let Some((wi, weight, pdf)) = sample_dielectric(&dist, eta, &wo, (rng.gen(), rng.gen()), rng.gen()) {
+                    assert_eq!(pdf, pdf_dielectric(&dist, eta, &wo, &wi));
+                    let expected = eval_dielectric(&dist, eta, &wo, &wi) / pdf;
+                    assert!((weight - expected).abs() < 1e-6 * expected.max(1.0), "{} {}", weight, expected);
+                }
+            }
+        }
+        let smooth = TrowbridgeReitz::new(0.0, 0.0);
+        let (wi, _, pdf) = sample_dielectric(&smooth, 1.5, &Vec3::new(0.0, 0.0, 1.0), (0.5, 0.5), 0.5).unwrap();
+        assert_eq!((wi.x, wi.y, wi.z), (0.0, 0.0, -1.0));
+        assert!((pdf - 0.96).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fresnel_dielectric() {
+        assert!((fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-12);
+        assert_eq!(fresnel_dielectric(1.0, 1.5), fresnel_dielectric(-1.0, 1.0 / 1.5));
+        // Past the critical angle from inside.
+        assert_eq!(fresnel_dielectric(0.5, 1.0 / 1.5), 1.0);
+        assert!((fresnel_dielectric(0.0, 1.5) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_refract() {
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let wo = spherical(0.5, 1.0);
+        let wi = refract(&wo, &n, 1.5).unwrap();
+        assert!((wi.length() - 1.0).abs() < 1e-12);
+        // Snell's law, with the refracted ray going through the surface.
+        let sin_o = (1.0 - wo.z * wo.z).sqrt();
+        let sin_i = (1.0 - wi.z * wi.z).sqrt();
+        assert!((sin_o - 1.5 * sin_i).abs() < 1e-12 && wi.z < 0.0);
+        assert!(refraction_half_vector(&wo, &wi, 1.5).is_some_and(|wm| (wm - n).length() < 1e-9));
+        assert!(refract(&spherical(1.2, 0.0), &n, 1.0 / 1.5).is_none());
+    }
+
+    #[test]
+    fn test_fresnel_conductor() {
+        let eta = Color::new(0.2, 0.9, 1.1);
+        let k = Color::new(3.9, 2.5, 2.1);
+        let normal = fresnel_conductor(1.0, eta, k);
+        // At normal incidence it reduces to ((n - 1)^2 + k^2) / ((n + 1)^2 + k^2).
+        let expected = ((0.2f64 - 1.0).powi(2) + 3.9f64.powi(2)) / ((0.2f64 + 1.0).powi(2) + 3.9f64.powi(2));
+        assert!((normal.r - expected).abs() < 1e-12);
+        let grazing = fresnel_conductor(0.0, eta, k);
+        assert!((grazing.g - 1.0).abs() < 1e-12);
+    }
+}
@@ -1,5 +1,5 @@
 use std::cell::UnsafeCell;
-use std::lazy::Lazy;
+use std::cell::LazyCell as Lazy;
 use rand_xoshiro::Xoshiro256Plus;
 use rand::SeedableRng;
 
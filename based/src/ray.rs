@@ -3,15 +3,37 @@ use glitz::vec::Vec3;
 pub struct Ray {
     pub o: Vec3,
     pub d: Vec3,
+    /// When the ray was fired, within the camera's shutter interval. Moving objects are
+    /// intersected where they are at this time.
+    pub time: f64,
+    /// Rays through the neighbouring pixels, for estimating how much of a surface this ray
+    /// stands for. Only camera rays and their specular bounces have them.
+    pub differential: Option<RayDifferential>,
+}
+
+/// Origins and directions of two rays offset from a main ray by one pixel in x and in y.
+#[derive(Debug, Clone, Copy)]
+pub struct RayDifferential {
+    pub rx_o: Vec3,
+    pub rx_d: Vec3,
+    pub ry_o: Vec3,
+    pub ry_d: Vec3,
 }
 
 impl Ray {
     pub fn new(o: Vec3, d: Vec3) -> Self {
-        Ray { o, d }
+        Ray { o, d, time: 0.0, differential: None }
+    }
+
+    pub fn differential(self, differential: Option<RayDifferential>) -> Self {
+        Ray { differential, ..self }
+    }
+
+    pub fn time(self, time: f64) -> Self {
+        Ray { time, ..self }
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
         self.o + t * self.d
     }
 }
-
@@ -1,33 +1,44 @@
-use crate::hittable::{intersect_spheres, Sphere};
+use crate::hittable::HittableList;
+use crate::hittable::bvh::Bvh;
+use crate::integrator::{DebugMode, DebugView, Integrator, PathTracer, Scene};
+use crate::light::Lights;
+use crate::medium::Medium;
 use crate::camera::Camera;
+use crate::environment::Environment;
 use xenon::color::Color;
-use crate::ray::Ray;
 use crate::random::with_rng;
 use std::fs::File;
-use xenon::write::fn_to_png;
+use xenon::write::{fn_to_png, fn_to_png_linear};
 use antsy::LoadingBar;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct Renderer<C: Camera + Sync> {
-    world: Vec<Sphere>,
+    world: Bvh,
     camera: C,
+    environment: Environment,
+    medium: Option<Box<dyn Medium>>,
     num_rays: AtomicUsize,
     image_width: u32,
     aspect_ratio: f64,
-    max_depth: u16,
+    path_tracer: PathTracer,
+    // Replaces `path_tracer` if set.
+    integrator: Option<Box<dyn Integrator>>,
     num_samples: u16,
 }
 
 impl<C: Camera + Sync> Renderer<C> {
-    pub fn new(world: Vec<Sphere>, camera: C) -> Self {
+    pub fn new(world: HittableList, camera: C) -> Self {
         Renderer {
-            world,
+            world: Bvh::new(world.into_objects()),
             camera,
+            environment: Environment::default(),
+            medium: None,
             num_rays: AtomicUsize::new(0),
             image_width: 800,
             aspect_ratio: 16.0 / 9.0,
-            max_depth: 50,
+            path_tracer: PathTracer::new(),
+            integrator: None,
             num_samples: 100,
         }
     }
@@ -44,44 +55,82 @@ impl<C: Camera + Sync> Renderer<C> {
         Renderer {num_samples, ..self}
     }
 
+    pub fn environment(self, environment: Environment) -> Self {
+        Renderer {environment, ..self}
+    }
+
+    /// Fills all space outside any `Volume` with `medium`, like fog. Light from the
+    /// environment, being infinitely far away, then never gets through, except in channels
+    /// the medium doesn't attenuate.
+    pub fn medium(self, medium: impl Medium + 'static) -> Self {
+        Renderer {medium: Some(Box::new(medium)), ..self}
+    }
+
+    /// Number of bounces every path of the default `PathTracer` makes, unless it leaves the
+    /// scene or is absorbed, before Russian roulette may end it.
+    pub fn min_depth(self, min_depth: u16) -> Self {
+        Renderer {path_tracer: self.path_tracer.min_depth(min_depth), ..self}
+    }
+
+    /// Number of rays after which a path of the default `PathTracer` is cut off regardless.
+    pub fn max_depth(self, max_depth: u16) -> Self {
+        Renderer {path_tracer: self.path_tracer.max_depth(max_depth), ..self}
+    }
+
+    /// How light is traced through the scene, by default a `PathTracer` set up by
+    /// `min_depth` and `max_depth`.
+    pub fn integrator(self, integrator: impl Integrator + 'static) -> Self {
+        Renderer {integrator: Some(Box::new(integrator)), ..self}
+    }
+
     pub fn render_to_file(self, filename: &str) {
+        let integrator = self.integrator.as_deref().unwrap_or(&self.path_tracer);
+        self.render(integrator, filename, false);
+    }
+
+    /// Renders each of `modes` with a `DebugView` into an image of its own, named after
+    /// `prefix` and the mode, as in `prefix_normals.png`. Values are stored as they are
+    /// rather than gamma encoded.
+    pub fn render_debug_to_files(self, prefix: &str, modes: &[DebugMode]) {
+        for mode in modes {
+            self.render(&DebugView::new(*mode), &format!("{}_{}.png", prefix, mode.name()), true);
+        }
+    }
+
+    fn render(&self, integrator: &dyn Integrator, filename: &str, linear: bool) {
         let file = File::create(filename).unwrap();
         let image_height = (self.image_width as f64 / self.aspect_ratio) as u32;
 
         let mut loadingbar = Mutex::new(LoadingBar::new(image_height, self.image_width).unwrap());
+        let lights = Lights::new(&self.world, &self.environment);
+        let scene = Scene::new(&self.world, &self.environment, &lights, self.medium.as_deref(), &self.num_rays);
 
-        fn_to_png(self.image_width, image_height, file, |i, j| {
+        // Each sample only needs to account for its share of the pixel, as the samples
+        // together already average over it. Shrinking too far just gives up filtering.
+        let footprint_scale = (1.0 / (self.num_samples as f64).sqrt()).max(0.125);
+        let pixel = (
+            footprint_scale / (self.image_width - 1) as f64,
+            footprint_scale / (image_height - 1) as f64,
+        );
+
+        let render_pixel = |i, j| {
             loadingbar.lock().unwrap().advance().unwrap();
-            (0..(self.num_samples + 1)).map(|_| {
+            (0..self.num_samples).map(|_| {
                 let u = (i as f64 + with_rng(rand::Rng::gen::<f64>)) / (self.image_width - 1) as f64;
                 let v = (j as f64 + with_rng(rand::Rng::gen::<f64>)) / (image_height - 1) as f64;
-                let r = self.camera.make_ray(u, v);
-                self.ray_color(r, self.max_depth)
+                let r = self.camera.make_ray(u, v, pixel);
+                integrator.li(r, &scene)
             }).sum::<Color>() / self.num_samples as f64
-        });
+        };
+        if linear {
+            fn_to_png_linear(self.image_width, image_height, file, render_pixel);
+        } else {
+            fn_to_png(self.image_width, image_height, file, render_pixel);
+        }
         loadingbar.get_mut().unwrap().advance().unwrap();
         let elapsed = loadingbar.into_inner().unwrap().get_elapsed().as_secs_f64();
-        let num_rays = self.num_rays.into_inner();
+        let num_rays = self.num_rays.swap(0, Ordering::Relaxed);
         let time_str = format!("Took {:.4} seconds, shot {} rays, {:.4} mrays/s", elapsed, num_rays, num_rays as f64 / elapsed / 1_000_000.0);
         println!("{}", time_str);
-
-    }
-
-    fn ray_color(&self, r: Ray, depth: u16) -> Color {
-        self.num_rays.fetch_add(1, Ordering::Relaxed);
-        if depth == 0 {
-            Color::new(0.0, 0.0, 0.0)
-        } else if let Some(hit) = intersect_spheres(&self.world, &r, 0.00001, f64::INFINITY) {
-            if let Some((scattered_ray, atten)) = hit.mat.scatter(hit, r) {
-                atten * self.ray_color(scattered_ray, depth - 1)
-            } else {
-                Color::new(0.0, 0.0, 0.0)
-            }
-        } else {
-            let unit_dir = r.d.unit_vec();
-            let t = 0.5 * (unit_dir.y + 1.0);
-            (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
-        }
     }
 }
-
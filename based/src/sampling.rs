@@ -0,0 +1,164 @@
+use std::f64::consts::PI;
+use glitz::vec::Vec3;
+
+/// Maps uniform `u` to a direction in the hemisphere around z, with density proportional to
+/// its cosine with z, which is `z / PI`.
+pub fn cosine_hemisphere(u: (f64, f64)) -> Vec3 {
+    let r = u.0.sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - u.0).max(0.0).sqrt())
+}
+
+/// Maps uniform `u` to a direction with the same density, `1 / (4 PI)`, in every direction.
+pub fn uniform_sphere(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Piecewise-constant distribution over `[0, 1)`, sampled by inverting its CDF.
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_int: f64,
+}
+
+impl Distribution1D {
+    /// Distribution proportional to `func`, whose entries must be non-negative.
+    pub fn new(func: Vec<f64>) -> Self {
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for i in 0..n {
+            cdf.push(cdf[i] + func[i] / n as f64);
+        }
+        let func_int = cdf[n];
+        if func_int == 0.0 {
+            // Nothing to prefer, so fall back to uniform.
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as f64 / n as f64;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= func_int;
+            }
+        }
+        Distribution1D { func, cdf, func_int }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Integral of the function over `[0, 1)`.
+    pub fn integral(&self) -> f64 {
+        self.func_int
+    }
+
+    /// Maps uniform `u` to `(x, pdf, segment)`, where `segment` is the index of the piece `x` is in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let offset = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(self.count() - 1);
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+        let x = (offset as f64 + du) / self.count() as f64;
+        (x, self.pdf_segment(offset), offset)
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        let offset = ((x * self.count() as f64) as usize).min(self.count() - 1);
+        self.pdf_segment(offset)
+    }
+
+    fn pdf_segment(&self, offset: usize) -> f64 {
+        if self.func_int == 0.0 { 1.0 } else { self.func[offset] / self.func_int }
+    }
+}
+
+/// Piecewise-constant distribution over `[0, 1)^2`, given as rows of values. Picks a row by
+/// its total, then a column within it.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        let conditional = func.chunks_exact(width).take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect::<Vec<_>>();
+        let marginal = Distribution1D::new(conditional.iter().map(Distribution1D::integral).collect());
+        Distribution2D { conditional, marginal }
+    }
+
+    /// Maps uniform `u` to a point `(x, y)` and its density.
+    pub fn sample(&self, u: (f64, f64)) -> ((f64, f64), f64) {
+        let (y, pdf_y, row) = self.marginal.sample(u.1);
+        let (x, pdf_x, _) = self.conditional[row].sample(u.0);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, p: (f64, f64)) -> f64 {
+        let rows = self.conditional.len();
+        let row = ((p.1 * rows as f64) as usize).min(rows - 1);
+        let cols = self.conditional[row].count();
+        let col = ((p.0 * cols as f64) as usize).min(cols - 1);
+        if self.marginal.integral() == 0.0 {
+            1.0
+        } else {
+            self.conditional[row].func[col] / self.marginal.integral()
+        }
+    }
+}
+
+/// Unit direction at angle `theta` from z and `phi` around it, for tests that sweep over
+/// the sphere of directions.
+#[cfg(test)]
+pub(crate) fn spherical(theta: f64, phi: f64) -> Vec3 {
+    Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos())
+}
+
+#[cfg(test)]
+mod sampling_tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_sampling() {
+        for &u in &[(0.0, 0.0), (0.5, 0.25), (0.99, 0.7)] {
+            let w = cosine_hemisphere(u);
+            assert!((w.length() - 1.0).abs() < 1e-12);
+            assert!(w.z >= 0.0);
+        }
+        assert_eq!(cosine_hemisphere((0.0, 0.3)).z, 1.0);
+        assert!((uniform_sphere((0.3, 0.8)).length() - 1.0).abs() < 1e-12);
+        assert_eq!(uniform_sphere((1.0, 0.0)).z, -1.0);
+    }
+
+    #[test]
+    fn test_distribution_1d() {
+        let d = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+        assert_eq!(d.integral(), 2.0);
+        let (x, pdf, offset) = d.sample(0.0);
+        assert_eq!((x, pdf, offset), (0.0, 0.5, 0));
+        let (x, pdf, offset) = d.sample(0.25);
+        assert!((x - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!((pdf, offset), (1.5, 1));
+        // Zero segments are never picked.
+        let (x, _, offset) = d.sample(0.5);
+        assert_eq!((x, offset), (0.75, 3));
+        assert_eq!(d.pdf(0.6), 0.0);
+    }
+
+    #[test]
+    fn test_distribution_2d() {
+        let d = Distribution2D::new(&[1.0, 1.0, 0.0, 2.0], 2, 2);
+        let ((x, y), pdf) = d.sample((0.5, 0.9));
+        assert!(x >= 0.5 && y >= 0.5);
+        assert_eq!(pdf, 2.0);
+        assert_eq!(pdf, d.pdf((x, y)));
+        assert_eq!(d.pdf((0.25, 0.75)), 0.0);
+    }
+}
@@ -0,0 +1,589 @@
+use std::path::Path;
+use std::sync::Arc;
+use glitz::noise::{self, Noise};
+use glitz::vec::Vec3;
+use xenon::color::Color;
+use crate::image::Image;
+use crate::import::ImportError;
+use crate::import::png::{load_png, ColorSpace};
+
+/// How texture lookup positions change from one pixel to the next, found from ray
+/// differentials. Zero when unknown, which makes lookups point samples.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Footprint {
+    pub dpdx: Vec3,
+    pub dpdy: Vec3,
+    pub duvdx: (f64, f64),
+    pub duvdy: (f64, f64),
+}
+
+/// A color that varies over a surface, looked up by texture coordinates and position.
+pub trait Texture: Send + Sync {
+    /// Color averaged over the area around `uv` and `p` that `footprint` spans, so that
+    /// detail finer than a pixel doesn't alias.
+    fn filtered(&self, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color;
+
+    /// Color at exactly `uv` and `p`.
+    fn value(&self, uv: (f64, f64), p: &Vec3) -> Color {
+        self.filtered(uv, p, &Footprint::default())
+    }
+}
+
+/// A plain color is the same everywhere.
+impl Texture for Color {
+    fn filtered(&self, _uv: (f64, f64), _p: &Vec3, _footprint: &Footprint) -> Color {
+        *self
+    }
+}
+
+/// Fraction of `[x - half_width, x + half_width]` covered by cells `[i, i + 1)` with odd `i`,
+/// or whether `x` is in one if the interval is empty.
+fn odd_fraction(x: f64, half_width: f64) -> f64 {
+    // Integral of the odd cell indicator from zero to `x`.
+    let odd_integral = |x: f64| {
+        let half = x / 2.0;
+        half.floor() + 2.0 * (half - half.floor() - 0.5).max(0.0)
+    };
+    if half_width == 0.0 {
+        x.floor().rem_euclid(2.0)
+    } else {
+        (odd_integral(x + half_width) - odd_integral(x - half_width)) / (2.0 * half_width)
+    }
+}
+
+/// Blends between the two textures of a checkerboard given the fraction of each axis spent
+/// in odd cells. A cell is odd when the sum of its indices is, so treating the axes as
+/// independent, the odd fraction follows from the product of the per axis parities.
+fn checker_blend(even: &dyn Texture, odd: &dyn Texture, odd_fractions: &[f64], uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+    let t = 0.5 * (1.0 - odd_fractions.iter().map(|f| 1.0 - 2.0 * f).product::<f64>());
+    if t <= 0.0 {
+        even.filtered(uv, p, footprint)
+    } else if t >= 1.0 {
+        odd.filtered(uv, p, footprint)
+    } else {
+        (1.0 - t) * even.filtered(uv, p, footprint) + t * odd.filtered(uv, p, footprint)
+    }
+}
+
+/// Alternates between two textures in cubes of side `1 / scale` filling space, so it
+/// needs no texture coordinates and looks the same on any shape. Box filtered over the
+/// footprint, so it fades to the average of the two in the distance instead of aliasing.
+pub struct Checker {
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    scale: f64,
+}
+
+impl Checker {
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, scale: f64) -> Self {
+        Checker { even, odd, scale }
+    }
+}
+
+impl Texture for Checker {
+    fn filtered(&self, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+        let (dx, dy) = (footprint.dpdx, footprint.dpdy);
+        let odd = [
+            odd_fraction(self.scale * p.x, self.scale * dx.x.abs().max(dy.x.abs())),
+            odd_fraction(self.scale * p.y, self.scale * dx.y.abs().max(dy.y.abs())),
+            odd_fraction(self.scale * p.z, self.scale * dx.z.abs().max(dy.z.abs())),
+        ];
+        checker_blend(self.even.as_ref(), self.odd.as_ref(), &odd, uv, p, footprint)
+    }
+}
+
+/// Alternates between two textures in squares of side `1 / scale` in texture space, box
+/// filtered like `Checker`.
+pub struct UvChecker {
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    scale: f64,
+}
+
+impl UvChecker {
+    pub fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, scale: f64) -> Self {
+        UvChecker { even, odd, scale }
+    }
+}
+
+impl Texture for UvChecker {
+    fn filtered(&self, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+        let (dx, dy) = (footprint.duvdx, footprint.duvdy);
+        let odd = [
+            odd_fraction(self.scale * uv.0, self.scale * dx.0.abs().max(dy.0.abs())),
+            odd_fraction(self.scale * uv.1, self.scale * dx.1.abs().max(dy.1.abs())),
+        ];
+        checker_blend(self.even.as_ref(), self.odd.as_ref(), &odd, uv, p, footprint)
+    }
+}
+
+/// How texels are looked up between their centers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    /// Blends the four nearest texels.
+    Bilinear,
+    /// Blends bilinear lookups in the two MIP levels whose texels best match the footprint.
+    Trilinear,
+    /// Weights texels under the elliptical footprint with a Gaussian, picking a MIP level by
+    /// the ellipse's minor axis. Sharper than trilinear at grazing angles.
+    Ewa,
+}
+
+/// What lies outside the unit square of texture space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    /// Extends the edge texels outwards.
+    Clamp,
+    /// Repeats, flipping every other copy so that edges meet seamlessly.
+    Mirror,
+}
+
+impl Wrap {
+    /// Maps any texel index along an axis of length `n` into `0..n`.
+    #[inline]
+    fn apply(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        let i = match self {
+            Wrap::Repeat => i.rem_euclid(n),
+            Wrap::Clamp => i.clamp(0, n - 1),
+            Wrap::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n { i } else { 2 * n - 1 - i }
+            }
+        };
+        i as usize
+    }
+}
+
+/// Ellipses more eccentric than this are widened, bounding the number of texels per lookup.
+const MAX_ANISOTROPY: f64 = 8.0;
+
+/// An image stretched over the unit square of texture space, with `v` running from the
+/// bottom row up. Texture coordinates are scaled then offset before the lookup, so a scale
+/// of 2 fits two copies across the surface. Filters with EWA and repeats by default.
+pub struct ImageTexture {
+    // MIP pyramid, each level half the size of the one before, down to a single texel.
+    levels: Vec<Arc<Image>>,
+    filter: Filter,
+    wrap: Wrap,
+    scale: (f64, f64),
+    offset: (f64, f64),
+}
+
+impl ImageTexture {
+    /// Texture showing `image`, which must have at least one pixel.
+    pub fn new(image: Arc<Image>) -> Self {
+        let (width, height) = (image.width(), image.height());
+        assert!(width > 0 && height > 0, "image texture needs at least one pixel, got {}x{}", width, height);
+        let mut levels = vec![image];
+        loop {
+            let last = &levels[levels.len() - 1];
+            if last.width() == 1 && last.height() == 1 {
+                break;
+            }
+            let next = downsample(last);
+            levels.push(Arc::new(next));
+        }
+        ImageTexture { levels, filter: Filter::Ewa, wrap: Wrap::Repeat, scale: (1.0, 1.0), offset: (0.0, 0.0) }
+    }
+
+    /// Loads a PNG color texture, decoding it from sRGB.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        Ok(Self::new(Arc::new(load_png(path, ColorSpace::Srgb)?)))
+    }
+
+    pub fn filter(self, filter: Filter) -> Self {
+        ImageTexture { filter, ..self }
+    }
+
+    pub fn wrap(self, wrap: Wrap) -> Self {
+        ImageTexture { wrap, ..self }
+    }
+
+    pub fn scale(self, scale: (f64, f64)) -> Self {
+        ImageTexture { scale, ..self }
+    }
+
+    pub fn offset(self, offset: (f64, f64)) -> Self {
+        ImageTexture { offset, ..self }
+    }
+
+    pub fn image(&self) -> &Arc<Image> {
+        &self.levels[0]
+    }
+
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    #[inline]
+    fn texel(&self, level: usize, x: i64, y: i64) -> Color {
+        let image = &self.levels[level];
+        image.get(self.wrap.apply(x, image.width()), self.wrap.apply(y, image.height()))
+    }
+
+    // Lookups below take `(s, t)` in texture space flipped to run from the top, like the image rows.
+
+    fn nearest(&self, level: usize, s: f64, t: f64) -> Color {
+        let image = &self.levels[level];
+        self.texel(level, (s * image.width() as f64).floor() as i64, (t * image.height() as f64).floor() as i64)
+    }
+
+    fn bilinear(&self, level: usize, s: f64, t: f64) -> Color {
+        let image = &self.levels[level];
+        // Continuous texel coordinates, with texel centers at integers.
+        let x = s * image.width() as f64 - 0.5;
+        let y = t * image.height() as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        (1.0 - dy) * ((1.0 - dx) * self.texel(level, x0, y0) + dx * self.texel(level, x0 + 1, y0))
+            + dy * ((1.0 - dx) * self.texel(level, x0, y0 + 1) + dx * self.texel(level, x0 + 1, y0 + 1))
+    }
+
+    /// Continuous MIP level whose texels are `width` wide in texture space.
+    fn level_for_width(&self, width: f64) -> f64 {
+        let resolution = self.levels[0].width().max(self.levels[0].height()) as f64;
+        (width * resolution).max(1e-8).log2().clamp(0.0, (self.levels.len() - 1) as f64)
+    }
+
+    fn trilinear(&self, s: f64, t: f64, width: f64) -> Color {
+        let level = self.level_for_width(width);
+        let lower = level.floor() as usize;
+        let frac = level - lower as f64;
+        if frac == 0.0 {
+            self.bilinear(lower, s, t)
+        } else {
+            (1.0 - frac) * self.bilinear(lower, s, t) + frac * self.bilinear(lower + 1, s, t)
+        }
+    }
+
+    fn ewa(&self, s: f64, t: f64, mut major: (f64, f64), mut minor: (f64, f64)) -> Color {
+        let length = |v: (f64, f64)| (v.0 * v.0 + v.1 * v.1).sqrt();
+        if length(major) < length(minor) {
+            std::mem::swap(&mut major, &mut minor);
+        }
+        let major_length = length(major);
+        let mut minor_length = length(minor);
+        if minor_length * MAX_ANISOTROPY < major_length && minor_length > 0.0 {
+            let scale = major_length / (minor_length * MAX_ANISOTROPY);
+            minor = (minor.0 * scale, minor.1 * scale);
+            minor_length *= scale;
+        }
+        if minor_length == 0.0 {
+            return self.bilinear(0, s, t);
+        }
+
+        // The top level is a single texel, the average of the whole image, and an ellipse
+        // as big as the clamped footprint would only visit it over and over.
+        let level = self.level_for_width(minor_length);
+        let top = self.levels.len() - 1;
+        if level >= top as f64 {
+            return self.texel(top, 0, 0);
+        }
+        let lower = level.floor() as usize;
+        let frac = level - lower as f64;
+        let at_lower = self.ewa_level(lower, s, t, major, minor);
+        if frac == 0.0 {
+            at_lower
+        } else {
+            (1.0 - frac) * at_lower + frac * self.ewa_level(lower + 1, s, t, major, minor)
+        }
+    }
+
+    fn ewa_level(&self, level: usize, s: f64, t: f64, d0: (f64, f64), d1: (f64, f64)) -> Color {
+        let image = &self.levels[level];
+        let (w, h) = (image.width() as f64, image.height() as f64);
+        // Continuous texel coordinates of the center, with texel centers at integers.
+        let (x, y) = (s * w - 0.5, t * h - 0.5);
+        let (d0, d1) = ((d0.0 * w, d0.1 * h), (d1.0 * w, d1.1 * h));
+
+        // Implicit ellipse `a s^2 + b s t + c t^2 = 1` spanned by the two axes, each
+        // coefficient padded by a texel so the ellipse always covers one.
+        let a = d0.1 * d0.1 + d1.1 * d1.1 + 1.0;
+        let b = -2.0 * (d0.0 * d0.1 + d1.0 * d1.1);
+        let c = d0.0 * d0.0 + d1.0 * d1.0 + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        let (a, b, c) = (a * inv_f, b * inv_f, c * inv_f);
+
+        let det = 4.0 * a * c - b * b;
+        let s_extent = 2.0 * (det * c).sqrt() / det;
+        let t_extent = 2.0 * (det * a).sqrt() / det;
+        let (x0, x1) = ((x - s_extent).ceil() as i64, (x + s_extent).floor() as i64);
+        let (y0, y1) = ((y - t_extent).ceil() as i64, (y + t_extent).floor() as i64);
+
+        const ALPHA: f64 = 2.0;
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        let mut total_weight = 0.0;
+        for it in y0..=y1 {
+            let tt = it as f64 - y;
+            for is in x0..=x1 {
+                let ss = is as f64 - x;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+                if r2 < 1.0 {
+                    let weight = (-ALPHA * r2).exp() - (-ALPHA).exp();
+                    sum += weight * self.texel(level, is, it);
+                    total_weight += weight;
+                }
+            }
+        }
+        if total_weight > 0.0 { sum / total_weight } else { self.bilinear(level, s, t) }
+    }
+}
+
+/// Halves an image along each axis, averaging blocks of up to four pixels.
+fn downsample(image: &Image) -> Image {
+    let width = image.width().div_ceil(2);
+    let height = image.height().div_ceil(2);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let xs = (2 * x..(2 * x + 2).min(image.width())).collect::<Vec<_>>();
+            let ys = 2 * y..(2 * y + 2).min(image.height());
+            let count = xs.len() * ys.len();
+            let sum = ys.flat_map(|y| xs.iter().map(move |&x| image.get(x, y))).sum::<Color>();
+            pixels.push(sum / count as f64);
+        }
+    }
+    Image::new(width, height, pixels)
+}
+
+impl Texture for ImageTexture {
+    fn filtered(&self, uv: (f64, f64), _p: &Vec3, footprint: &Footprint) -> Color {
+        let s = uv.0 * self.scale.0 + self.offset.0;
+        let t = 1.0 - (uv.1 * self.scale.1 + self.offset.1);
+        let dx = (footprint.duvdx.0 * self.scale.0, -footprint.duvdx.1 * self.scale.1);
+        let dy = (footprint.duvdy.0 * self.scale.0, -footprint.duvdy.1 * self.scale.1);
+        match self.filter {
+            Filter::Nearest => self.nearest(0, s, t),
+            Filter::Bilinear => self.bilinear(0, s, t),
+            Filter::Trilinear => {
+                let width = 2.0 * dx.0.abs().max(dx.1.abs()).max(dy.0.abs()).max(dy.1.abs());
+                self.trilinear(s, t, width)
+            }
+            Filter::Ewa => self.ewa(s, t, dx, dy),
+        }
+    }
+}
+
+/// Blends between `a` at `t = 0` and `b` at `t = 1`.
+fn mix(a: &dyn Texture, b: &dyn Texture, t: f64, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    (1.0 - t) * a.filtered(uv, p, footprint) + t * b.filtered(uv, p, footprint)
+}
+
+/// Fractal noise mapped from `[-1, 1]` onto a blend between two textures. With `Worley`
+/// noise, whose values start at zero, only the upper half of the blend gets used.
+pub struct NoiseTexture {
+    noise: Arc<dyn Noise>,
+    scale: f64,
+    octaves: u32,
+    a: Arc<dyn Texture>,
+    b: Arc<dyn Texture>,
+}
+
+impl NoiseTexture {
+    pub fn new(noise: Arc<dyn Noise>, scale: f64, a: Arc<dyn Texture>, b: Arc<dyn Texture>) -> Self {
+        NoiseTexture { noise, scale, octaves: 1, a, b }
+    }
+
+    pub fn octaves(self, octaves: u32) -> Self {
+        NoiseTexture { octaves, ..self }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn filtered(&self, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+        let n = noise::fbm(self.noise.as_ref(), &(self.scale * *p), self.octaves, 2.0, 0.5);
+        mix(self.a.as_ref(), self.b.as_ref(), 0.5 * (1.0 + n), uv, p, footprint)
+    }
+}
+
+/// Turbulent noise, the billowy look of smoke or clouds, blending from `a` where it's calm to `b`.
+pub struct Turbulence {
+    noise: Arc<dyn Noise>,
+    scale: f64,
+    octaves: u32,
+    a: Arc<dyn Texture>,
+    b: Arc<dyn Texture>,
+}
+
+impl Turbulence {
+    pub fn new(noise: Arc<dyn Noise>, scale: f64, a: Arc<dyn Texture>, b: Arc<dyn Texture>) -> Self {
+        Turbulence { noise, scale, octaves: 7, a, b }
+    }
+
+    pub fn octaves(self, octaves: u32) -> Self {
+        Turbulence { octaves, ..self }
+    }
+}
+
+impl Texture for Turbulence {
+    fn filtered(&self, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+        let t = noise::turbulence(self.noise.as_ref(), &(self.scale * *p), self.octaves, 2.0, 0.5);
+        mix(self.a.as_ref(), self.b.as_ref(), t, uv, p, footprint)
+    }
+}
+
+/// Veins of `b` through `a`, running across the z axis and bent by `turbulence` times as much
+/// turbulent noise.
+pub struct Marble {
+    noise: Arc<dyn Noise>,
+    scale: f64,
+    turbulence: f64,
+    a: Arc<dyn Texture>,
+    b: Arc<dyn Texture>,
+}
+
+impl Marble {
+    pub fn new(noise: Arc<dyn Noise>, scale: f64, turbulence: f64, a: Arc<dyn Texture>, b: Arc<dyn Texture>) -> Self {
+        Marble { noise, scale, turbulence, a, b }
+    }
+}
+
+impl Texture for Marble {
+    fn filtered(&self, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+        let q = self.scale * *p;
+        let turb = noise::turbulence(self.noise.as_ref(), &q, 7, 2.0, 0.5);
+        let vein = (q.z + self.turbulence * turb).sin();
+        mix(self.a.as_ref(), self.b.as_ref(), 1.0 - vein.abs(), uv, p, footprint)
+    }
+}
+
+/// Growth rings around the y axis, `1 / scale` apart, going from `a` in early wood to `b` in
+/// late wood and warped by `turbulence` times as much fractal noise.
+pub struct Wood {
+    noise: Arc<dyn Noise>,
+    scale: f64,
+    turbulence: f64,
+    a: Arc<dyn Texture>,
+    b: Arc<dyn Texture>,
+}
+
+impl Wood {
+    pub fn new(noise: Arc<dyn Noise>, scale: f64, turbulence: f64, a: Arc<dyn Texture>, b: Arc<dyn Texture>) -> Self {
+        Wood { noise, scale, turbulence, a, b }
+    }
+}
+
+impl Texture for Wood {
+    fn filtered(&self, uv: (f64, f64), p: &Vec3, footprint: &Footprint) -> Color {
+        let q = self.scale * *p;
+        let warp = noise::fbm(self.noise.as_ref(), &q, 4, 2.0, 0.5);
+        let rings = (q.x * q.x + q.z * q.z).sqrt() + self.turbulence * warp;
+        // Rings darken gradually through the year, then switch back abruptly.
+        let t = (rings - rings.floor()).powi(3);
+        mix(self.a.as_ref(), self.b.as_ref(), t, uv, p, footprint)
+    }
+}
+
+#[cfg(test)]
+mod texture_tests {
+    use super::*;
+
+    #[test]
+    fn test_checker() {
+        let black: Arc<dyn Texture> = Arc::new(Color::new(0.0, 0.0, 0.0));
+        let white: Arc<dyn Texture> = Arc::new(Color::new(1.0, 1.0, 1.0));
+        let checker = Checker::new(black.clone(), white.clone(), 2.0);
+        assert_eq!(checker.value((0.0, 0.0), &Vec3::new(0.1, 0.1, 0.1)).r, 0.0);
+        assert_eq!(checker.value((0.0, 0.0), &Vec3::new(0.6, 0.1, 0.1)).r, 1.0);
+        assert_eq!(checker.value((0.0, 0.0), &Vec3::new(-0.1, 0.1, 0.1)).r, 1.0);
+
+        let checker = UvChecker::new(black, white, 4.0);
+        assert_eq!(checker.value((0.1, 0.1), &Vec3::default()).r, 0.0);
+        assert_eq!(checker.value((0.3, 0.1), &Vec3::default()).r, 1.0);
+    }
+
+    #[test]
+    fn test_image_texture() {
+        let pixels = vec![
+            Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0), Color::new(1.0, 1.0, 1.0),
+        ];
+        let texture = ImageTexture::new(Arc::new(Image::new(2, 2, pixels)));
+        let p = Vec3::default();
+        // v = 0 is the bottom row.
+        assert_eq!(texture.value((0.25, 0.25), &p).b, 1.0);
+        assert_eq!(texture.value((0.75, 0.75), &p).g, 1.0);
+        assert_eq!(texture.value((1.25, -0.75), &p).b, 1.0);
+
+        // Halfway between the bottom texels.
+        let c = texture.value((0.5, 0.25), &p);
+        assert_eq!((c.r, c.g, c.b), (0.5, 0.5, 1.0));
+        let nearest = ImageTexture::new(texture.image().clone()).filter(Filter::Nearest);
+        assert_eq!(nearest.value((0.49, 0.25), &p).r, 0.0);
+
+        let shifted = ImageTexture::new(texture.image().clone()).scale((2.0, 1.0)).offset((0.5, 0.0));
+        assert_eq!(shifted.value((0.125, 0.25), &p).r, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one pixel")]
+    fn test_empty_image() {
+        // Would never shrink to the single texel the MIP pyramid stops at.
+        ImageTexture::new(Arc::new(Image::new(0, 4, vec![])));
+    }
+
+    #[test]
+    fn test_filtered_minification() {
+        // A one texel checkerboard averages out to grey once the footprint spans many texels.
+        let size = 16;
+        let pixels = (0..size * size)
+            .map(|i| if (i % size + i / size) % 2 == 0 { Color::new(0.0, 0.0, 0.0) } else { Color::new(1.0, 1.0, 1.0) })
+            .collect();
+        let texture = ImageTexture::new(Arc::new(Image::new(size, size, pixels)));
+        assert_eq!(texture.num_levels(), 5);
+
+        let p = Vec3::default();
+        let wide = Footprint { duvdx: (0.3, 0.0), duvdy: (0.0, 0.3), ..Footprint::default() };
+        let narrow = Footprint { duvdx: (0.001, 0.0), duvdy: (0.0, 0.001), ..Footprint::default() };
+        // As at a grazing hit, which has to stop at the top of the pyramid rather than visit
+        // every texel under the footprint.
+        let huge = Footprint { duvdx: (1e6, 0.0), duvdy: (0.0, 1e5), ..Footprint::default() };
+        for filter in [Filter::Trilinear, Filter::Ewa].iter() {
+            let texture = ImageTexture::new(texture.image().clone()).filter(*filter);
+            assert!((texture.filtered((0.3, 0.6), &p, &wide).r - 0.5).abs() < 0.05);
+            assert!((texture.filtered((0.3, 0.6), &p, &huge).r - 0.5).abs() < 1e-9);
+            assert!(texture.filtered((0.5 / 16.0, 1.0 - 0.5 / 16.0), &p, &narrow).r < 0.01);
+        }
+
+        let black: Arc<dyn Texture> = Arc::new(Color::new(0.0, 0.0, 0.0));
+        let white: Arc<dyn Texture> = Arc::new(Color::new(1.0, 1.0, 1.0));
+        let checker = UvChecker::new(black.clone(), white.clone(), 16.0);
+        assert!((checker.filtered((0.3, 0.6), &p, &wide).r - 0.5).abs() < 0.05);
+        assert_eq!(checker.filtered((0.01, 0.01), &p, &narrow).r, 0.0);
+        let wide = Footprint { dpdx: Vec3::new(3.0, 0.0, 0.0), dpdy: Vec3::new(0.0, 0.0, 3.0), ..Footprint::default() };
+        let checker = Checker::new(black, white, 4.0);
+        assert!((checker.filtered((0.0, 0.0), &Vec3::new(0.1, 0.1, 0.1), &wide).r - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!((-5..5).map(|i| Wrap::Repeat.apply(i, 3)).collect::<Vec<_>>(), [1, 2, 0, 1, 2, 0, 1, 2, 0, 1]);
+        assert_eq!((-5..5).map(|i| Wrap::Clamp.apply(i, 3)).collect::<Vec<_>>(), [0, 0, 0, 0, 0, 0, 1, 2, 2, 2]);
+        assert_eq!((-5..5).map(|i| Wrap::Mirror.apply(i, 3)).collect::<Vec<_>>(), [1, 2, 2, 1, 0, 0, 1, 2, 2, 1]);
+    }
+
+    #[test]
+    fn test_procedural_in_range() {
+        let black: Arc<dyn Texture> = Arc::new(Color::new(0.0, 0.0, 0.0));
+        let white: Arc<dyn Texture> = Arc::new(Color::new(1.0, 1.0, 1.0));
+        let perlin: Arc<dyn Noise> = Arc::new(glitz::noise::Perlin::new(0));
+        let textures: Vec<Box<dyn Texture>> = vec![
+            Box::new(NoiseTexture::new(perlin.clone(), 4.0, black.clone(), white.clone()).octaves(5)),
+            Box::new(Turbulence::new(perlin.clone(), 4.0, black.clone(), white.clone())),
+            Box::new(Marble::new(perlin.clone(), 4.0, 10.0, black.clone(), white.clone())),
+            Box::new(Wood::new(perlin, 4.0, 0.5, black, white)),
+        ];
+        for texture in &textures {
+            for i in 0..100 {
+                let p = Vec3::new(i as f64 * 0.31, i as f64 * -0.17, i as f64 * 0.07);
+                let c = texture.value((0.0, 0.0), &p);
+                assert!((0.0..=1.0).contains(&c.r));
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::vec::Vec3;
+
+/// Axis-aligned bounding box, stored as its minimum and maximum corners.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// A box containing nothing, the identity for `union`.
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3 { x: f64::INFINITY, y: f64::INFINITY, z: f64::INFINITY },
+        max: Vec3 { x: f64::NEG_INFINITY, y: f64::NEG_INFINITY, z: f64::NEG_INFINITY },
+    };
+
+    #[inline]
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Aabb {
+            min: a.min(&b),
+            max: a.max(&b),
+        }
+    }
+
+    #[inline]
+    pub fn union(&self, other: &Self) -> Self {
+        Aabb {
+            min: self.min.min(&other.min),
+            max: self.max.max(&other.max),
+        }
+    }
+
+    #[inline]
+    pub fn union_point(&self, p: &Vec3) -> Self {
+        Aabb {
+            min: self.min.min(p),
+            max: self.max.max(p),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    #[inline]
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    #[inline]
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    #[inline]
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            0.0
+        } else {
+            let d = self.diagonal();
+            2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+        }
+    }
+
+    // Index of the axis along which the box is widest.
+    #[inline]
+    pub fn longest_axis(&self) -> usize {
+        let d = self.diagonal();
+        if d.x > d.y && d.x > d.z {
+            0
+        } else if d.y > d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    // Position of `p` relative to the box corners, 0 at `min` and 1 at `max` on each axis.
+    #[inline]
+    pub fn offset(&self, p: &Vec3) -> Vec3 {
+        let mut o = *p - self.min;
+        if self.max.x > self.min.x { o.x /= self.max.x - self.min.x; }
+        if self.max.y > self.min.y { o.y /= self.max.y - self.min.y; }
+        if self.max.z > self.min.z { o.z /= self.max.z - self.min.z; }
+        o
+    }
+
+    // Slab test against a ray given by its origin and reciprocal direction.
+    #[inline]
+    pub fn intersect(&self, o: &Vec3, inv_d: &Vec3, tmin: f64, tmax: f64) -> bool {
+        let mut tmin = tmin;
+        let mut tmax = tmax;
+        for axis in 0..3 {
+            let mut t0 = (self.min[axis] - o[axis]) * inv_d[axis];
+            let mut t1 = (self.max[axis] - o[axis]) * inv_d[axis];
+            if inv_d[axis] < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // Written so that NaNs from 0 * inf leave the interval unchanged.
+            tmin = if t0 > tmin { t0 } else { tmin };
+            tmax = if t1 < tmax { t1 } else { tmax };
+            if tmax < tmin {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::EMPTY
+    }
+}
+
+#[cfg(test)]
+mod aabb_tests {
+    use super::*;
+
+    #[test]
+    fn test_union() {
+        let a = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Vec3::new(2.0, -1.0, 0.5), Vec3::new(3.0, 0.5, 0.5));
+        let u = a.union(&b);
+        assert_eq!(u.min, Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(u.max, Vec3::new(3.0, 1.0, 1.0));
+        assert_eq!(Aabb::EMPTY.union(&a), a);
+    }
+
+    #[test]
+    fn test_surface_area() {
+        let a = Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(a.surface_area(), 22.0);
+        assert_eq!(Aabb::EMPTY.surface_area(), 0.0);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let a = Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let o = Vec3::new(0.0, 0.0, -5.0);
+        let inv_d = Vec3::new(1.0 / 0.0, 1.0 / 0.0, 1.0);
+        assert!(a.intersect(&o, &inv_d, 0.0, f64::INFINITY));
+        assert!(!a.intersect(&o, &inv_d, 0.0, 3.0));
+        let inv_d = Vec3::new(1.0 / 0.0, 1.0 / 0.0, -1.0);
+        assert!(!a.intersect(&o, &inv_d, 0.0, f64::INFINITY));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::vec::Vec3;
+
+/// Orthonormal basis with `n` as its z axis, for working in a surface's local coordinates.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Frame {
+    pub t: Vec3,
+    pub b: Vec3,
+    pub n: Vec3,
+}
+
+impl Frame {
+    /// Frame around unit vector `n` with arbitrary but consistent tangents, using the
+    /// branchless construction of Duff et al. 2017.
+    pub fn from_normal(n: Vec3) -> Self {
+        let sign = 1.0f64.copysign(n.z);
+        let a = -1.0 / (sign + n.z);
+        let b = n.x * n.y * a;
+        Frame {
+            t: Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            b: Vec3::new(b, sign + n.y * n.y * a, -n.y),
+            n,
+        }
+    }
+
+    /// Frame around unit vector `n` whose x axis follows `tangent` projected onto the plane
+    /// perpendicular to `n`, falling back to `from_normal` if that leaves nothing.
+    pub fn from_normal_tangent(n: Vec3, tangent: Vec3) -> Self {
+        let t = tangent - n.dot(&tangent) * n;
+        let length = t.length();
+        if length < 1e-12 || !length.is_finite() {
+            return Self::from_normal(n);
+        }
+        let t = t / length;
+        Frame { t, b: n.cross(&t), n }
+    }
+
+    #[inline]
+    pub fn to_local(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(v.dot(&self.t), v.dot(&self.b), v.dot(&self.n))
+    }
+
+    #[inline]
+    pub fn to_world(&self, v: &Vec3) -> Vec3 {
+        v.x * self.t + v.y * self.b + v.z * self.n
+    }
+}
+
+#[cfg(test)]
+mod frame_tests {
+    use super::*;
+
+    fn assert_orthonormal(f: &Frame) {
+        for (a, b) in [(f.t, f.b), (f.b, f.n), (f.n, f.t)].iter() {
+            assert!(a.dot(b).abs() < 1e-12);
+        }
+        for v in [f.t, f.b, f.n].iter() {
+            assert!((v.length() - 1.0).abs() < 1e-12);
+        }
+        // Right handed, so that local z is the normal side.
+        assert!((f.t.cross(&f.b) - f.n).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_from_normal() {
+        for n in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(1.0, 2.0, -3.0).unit_vec()].iter() {
+            let f = Frame::from_normal(*n);
+            assert_orthonormal(&f);
+            let v = Vec3::new(0.3, -0.2, 0.9);
+            assert!((f.to_world(&f.to_local(&v)) - v).length() < 1e-12);
+            assert!((f.to_local(n).z - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_from_normal_tangent() {
+        let n = Vec3::new(0.0, 1.0, 0.0);
+        let f = Frame::from_normal_tangent(n, Vec3::new(2.0, 1.0, 0.0));
+        assert_orthonormal(&f);
+        assert!((f.t - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        assert_orthonormal(&Frame::from_normal_tangent(n, Vec3::new(0.0, 3.0, 0.0)));
+    }
+}
@@ -1,2 +1,6 @@
+pub mod aabb;
+pub mod frame;
+pub mod noise;
+pub mod transform;
 pub mod vec;
 
@@ -0,0 +1,301 @@
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use crate::vec::Vec3;
+
+/// A smooth pseudo-random function of position.
+pub trait Noise: Send + Sync {
+    fn noise(&self, p: &Vec3) -> f64;
+}
+
+/// A shuffled table of the bytes `0..256` that lattice points are hashed through. Two tables
+/// built from the same seed are identical, so noise built on them is reproducible.
+#[derive(Clone)]
+pub struct PermutationTable {
+    // Stored twice over so that sums of two entries can index it without wrapping.
+    perm: [u8; 512],
+}
+
+impl PermutationTable {
+    pub fn new(rng: &mut impl Rng) -> Self {
+        let mut shuffled = [0u8; 256];
+        for (i, p) in shuffled.iter_mut().enumerate() {
+            *p = i as u8;
+        }
+        shuffled.shuffle(rng);
+
+        let mut perm = [0u8; 512];
+        perm[..256].copy_from_slice(&shuffled);
+        perm[256..].copy_from_slice(&shuffled);
+        PermutationTable { perm }
+    }
+
+    pub fn from_seed(seed: u64) -> Self {
+        Self::new(&mut Xoshiro256PlusPlus::seed_from_u64(seed))
+    }
+
+    /// Hashes a lattice point to a byte.
+    #[inline]
+    pub fn hash(&self, x: i64, y: i64, z: i64) -> u8 {
+        let p = &self.perm;
+        let h = p[(x & 255) as usize] as usize + (y & 255) as usize;
+        let h = p[h] as usize + (z & 255) as usize;
+        p[h]
+    }
+}
+
+#[inline]
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Dot product of `(x, y, z)` with one of the twelve edge directions of a cube, picked by `hash`.
+#[inline]
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    match hash % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+/// Ken Perlin's improved gradient noise, zero at every lattice point and roughly within `[-1, 1]`.
+#[derive(Clone)]
+pub struct Perlin {
+    perm: PermutationTable,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        Perlin { perm: PermutationTable::from_seed(seed) }
+    }
+}
+
+impl Noise for Perlin {
+    fn noise(&self, p: &Vec3) -> f64 {
+        let (xi, yi, zi) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (x, y, z) = (p.x - xi, p.y - yi, p.z - zi);
+        let (xi, yi, zi) = (xi as i64, yi as i64, zi as i64);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let g = |dx: i64, dy: i64, dz: i64| {
+            let h = self.perm.hash(xi + dx, yi + dy, zi + dz);
+            grad(h, x - dx as f64, y - dy as f64, z - dz as f64)
+        };
+
+        lerp(w,
+            lerp(v, lerp(u, g(0, 0, 0), g(1, 0, 0)), lerp(u, g(0, 1, 0), g(1, 1, 0))),
+            lerp(v, lerp(u, g(0, 0, 1), g(1, 0, 1)), lerp(u, g(0, 1, 1), g(1, 1, 1))),
+        )
+    }
+}
+
+/// Ken Perlin's simplex noise, which sums contributions from the four corners of the
+/// tetrahedron containing a point rather than the eight of a cube. Cheaper than `Perlin` and
+/// without its axis-aligned artifacts, roughly within `[-1, 1]`.
+#[derive(Clone)]
+pub struct Simplex {
+    perm: PermutationTable,
+}
+
+impl Simplex {
+    pub fn new(seed: u64) -> Self {
+        Simplex { perm: PermutationTable::from_seed(seed) }
+    }
+}
+
+impl Noise for Simplex {
+    fn noise(&self, p: &Vec3) -> f64 {
+        const F3: f64 = 1.0 / 3.0;
+        const G3: f64 = 1.0 / 6.0;
+
+        // Skew into the lattice of cubes that each split into six tetrahedra.
+        let s = (p.x + p.y + p.z) * F3;
+        let (i, j, k) = ((p.x + s).floor(), (p.y + s).floor(), (p.z + s).floor());
+        let t = (i + j + k) * G3;
+        let x0 = p.x - (i - t);
+        let y0 = p.y - (j - t);
+        let z0 = p.z - (k - t);
+
+        // Find which tetrahedron we're in by ordering the offsets within the cube.
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 { (1, 0, 0, 1, 1, 0) }
+            else if x0 >= z0 { (1, 0, 0, 1, 0, 1) }
+            else { (0, 0, 1, 1, 0, 1) }
+        } else if y0 < z0 { (0, 0, 1, 0, 1, 1) }
+        else if x0 < z0 { (0, 1, 0, 0, 1, 1) }
+        else { (0, 1, 0, 1, 1, 0) };
+
+        let (i, j, k) = (i as i64, j as i64, k as i64);
+        let corner = |di: i64, dj: i64, dk: i64, offset: f64| {
+            let x = x0 - di as f64 + offset;
+            let y = y0 - dj as f64 + offset;
+            let z = z0 - dk as f64 + offset;
+            let t = 0.6 - x * x - y * y - z * z;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t2 = t * t;
+                t2 * t2 * grad(self.perm.hash(i + di, j + dj, k + dk), x, y, z)
+            }
+        };
+
+        32.0 * (corner(0, 0, 0, 0.0)
+            + corner(i1, j1, k1, G3)
+            + corner(i2, j2, k2, 2.0 * G3)
+            + corner(1, 1, 1, 3.0 * G3))
+    }
+}
+
+/// Steven Worley's cellular noise, built from one randomly placed feature point per unit cell.
+/// As a `Noise` it is the distance to the nearest feature point.
+#[derive(Clone)]
+pub struct Worley {
+    perm: PermutationTable,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Worley { perm: PermutationTable::from_seed(seed) }
+    }
+
+    fn feature_point(&self, i: i64, j: i64, k: i64) -> Vec3 {
+        let offset = |h: u8| (h as f64 + 0.5) / 256.0;
+        Vec3::new(
+            i as f64 + offset(self.perm.hash(i, j, k)),
+            j as f64 + offset(self.perm.hash(j, k, i)),
+            k as f64 + offset(self.perm.hash(k, i, j)),
+        )
+    }
+
+    /// Distances from `p` to the nearest and second nearest feature points.
+    pub fn distances(&self, p: &Vec3) -> (f64, f64) {
+        let (i, j, k) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+        let mut f1 = f64::INFINITY;
+        let mut f2 = f64::INFINITY;
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let d = self.feature_point(i + di, j + dj, k + dk) - *p;
+                    let dist_squared = d.dot(&d);
+                    if dist_squared < f1 {
+                        f2 = f1;
+                        f1 = dist_squared;
+                    } else if dist_squared < f2 {
+                        f2 = dist_squared;
+                    }
+                }
+            }
+        }
+        (f1.sqrt(), f2.sqrt())
+    }
+}
+
+impl Noise for Worley {
+    fn noise(&self, p: &Vec3) -> f64 {
+        self.distances(p).0
+    }
+}
+
+/// Fractional Brownian motion: `octaves` layers of `noise`, each `lacunarity` times the
+/// frequency and `gain` times the amplitude of the one before.
+pub fn fbm<N: Noise + ?Sized>(noise: &N, p: &Vec3, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+    let mut sum = 0.0;
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    for _ in 0..octaves {
+        sum += amplitude * noise.noise(&(frequency * *p));
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    sum
+}
+
+/// Like `fbm`, but summing the absolute value of each octave, which creases the result
+/// wherever the noise crosses zero.
+pub fn turbulence<N: Noise + ?Sized>(noise: &N, p: &Vec3, octaves: u32, lacunarity: f64, gain: f64) -> f64 {
+    let mut sum = 0.0;
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    for _ in 0..octaves {
+        sum += amplitude * noise.noise(&(frequency * *p)).abs();
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod noise_tests {
+    use super::*;
+
+    fn sample_points() -> impl Iterator<Item = Vec3> {
+        (0..1000).map(|i| {
+            let i = i as f64;
+            Vec3::new(i * 0.137 - 50.0, i * 0.071 + 3.3, -i * 0.053)
+        })
+    }
+
+    #[test]
+    fn test_permutation_table_seeded() {
+        let a = PermutationTable::from_seed(7);
+        let b = PermutationTable::from_seed(7);
+        let c = PermutationTable::from_seed(8);
+        assert!(a.perm[..] == b.perm[..]);
+        assert!(a.perm[..] != c.perm[..]);
+        let mut sorted = a.perm[..256].to_vec();
+        sorted.sort_unstable();
+        assert!(sorted.iter().enumerate().all(|(i, &p)| p as usize == i));
+    }
+
+    #[test]
+    fn test_perlin() {
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.noise(&Vec3::new(3.0, -2.0, 5.0)), 0.0);
+        assert_eq!(perlin.noise(&Vec3::new(0.3, 0.4, 0.5)), Perlin::new(1).noise(&Vec3::new(0.3, 0.4, 0.5)));
+        assert!(sample_points().all(|p| perlin.noise(&p).abs() <= 1.1));
+        assert!(sample_points().any(|p| perlin.noise(&p).abs() > 0.1));
+    }
+
+    #[test]
+    fn test_simplex() {
+        let simplex = Simplex::new(1);
+        assert!(sample_points().all(|p| simplex.noise(&p).abs() <= 1.1));
+        assert!(sample_points().any(|p| simplex.noise(&p).abs() > 0.1));
+    }
+
+    #[test]
+    fn test_worley() {
+        let worley = Worley::new(1);
+        let feature = worley.feature_point(2, -1, 0);
+        assert_eq!(worley.noise(&feature), 0.0);
+        for p in sample_points() {
+            let (f1, f2) = worley.distances(&p);
+            assert!(f1 <= f2);
+            // Every cell has a feature point, so the nearest is never beyond the cell's diagonal.
+            assert!(f1 <= 3.0f64.sqrt());
+        }
+    }
+
+    #[test]
+    fn test_fbm() {
+        let perlin = Perlin::new(3);
+        let p = Vec3::new(0.3, 0.7, 0.2);
+        assert_eq!(fbm(&perlin, &p, 1, 2.0, 0.5), perlin.noise(&p));
+        assert!(turbulence(&perlin, &p, 4, 2.0, 0.5) >= 0.0);
+    }
+}
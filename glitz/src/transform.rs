@@ -0,0 +1,416 @@
+use crate::aabb::Aabb;
+use crate::vec::Vec3;
+
+type Matrix = [[f64; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+/// An affine transformation, stored as a 4x4 matrix acting on column vectors along with
+/// its inverse, so that both directions are equally cheap.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Transform {
+    m: Matrix,
+    inv: Matrix,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { m: IDENTITY, inv: IDENTITY };
+
+    /// Transform for the affine matrix `m`, whose last row must be `[0, 0, 0, 1]`, or `None`
+    /// if it can't be inverted.
+    pub fn from_matrix(m: [[f64; 4]; 4]) -> Option<Self> {
+        // Invert the linear part by its adjugate, then undo the translation.
+        let a = |i: usize, j: usize| m[i][j];
+        let cofactor = |i: usize, j: usize| {
+            let (i0, i1) = ((i + 1) % 3, (i + 2) % 3);
+            let (j0, j1) = ((j + 1) % 3, (j + 2) % 3);
+            a(i0, j0) * a(i1, j1) - a(i0, j1) * a(i1, j0)
+        };
+        let det = a(0, 0) * cofactor(0, 0) + a(0, 1) * cofactor(0, 1) + a(0, 2) * cofactor(0, 2);
+        if det.abs() < 1e-300 || !det.is_finite() {
+            return None;
+        }
+        let mut inv = IDENTITY;
+        for (i, row) in inv.iter_mut().enumerate().take(3) {
+            for (j, v) in row.iter_mut().enumerate().take(3) {
+                *v = cofactor(j, i) / det;
+            }
+        }
+        for row in inv.iter_mut().take(3) {
+            row[3] = -(0..3).map(|k| row[k] * m[k][3]).sum::<f64>();
+        }
+        Some(Transform { m, inv })
+    }
+
+    pub fn translate(v: Vec3) -> Self {
+        let mut m = IDENTITY;
+        let mut inv = IDENTITY;
+        for i in 0..3 {
+            m[i][3] = v[i];
+            inv[i][3] = -v[i];
+        }
+        Transform { m, inv }
+    }
+
+    /// Scales by a factor along each axis, none of which may be zero.
+    pub fn scale(v: Vec3) -> Self {
+        let mut m = IDENTITY;
+        let mut inv = IDENTITY;
+        for i in 0..3 {
+            m[i][i] = v[i];
+            inv[i][i] = 1.0 / v[i];
+        }
+        Transform { m, inv }
+    }
+
+    /// Rotates counterclockwise by `angle` radians, looking down `axis` towards the origin.
+    pub fn rotate(axis: Vec3, angle: f64) -> Self {
+        Quaternion::from_axis_angle(axis, angle).to_transform()
+    }
+
+    /// This transform followed by `next`.
+    pub fn then(&self, next: &Transform) -> Self {
+        Transform { m: mul(&next.m, &self.m), inv: mul(&self.inv, &next.inv) }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Transform { m: self.inv, inv: self.m }
+    }
+
+    pub fn matrix(&self) -> &[[f64; 4]; 4] {
+        &self.m
+    }
+
+    #[inline]
+    pub fn point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    /// Transforms a direction, which translation leaves alone.
+    #[inline]
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// Transforms a surface normal by the inverse transpose, which keeps it perpendicular
+    /// to the transformed surface. The result isn't normalized.
+    #[inline]
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        let inv = &self.inv;
+        Vec3::new(
+            inv[0][0] * n.x + inv[1][0] * n.y + inv[2][0] * n.z,
+            inv[0][1] * n.x + inv[1][1] * n.y + inv[2][1] * n.z,
+            inv[0][2] * n.x + inv[1][2] * n.y + inv[2][2] * n.z,
+        )
+    }
+
+    /// Smallest box containing the transformed `bounds`.
+    pub fn bounds(&self, bounds: &Aabb) -> Aabb {
+        if bounds.is_empty() {
+            return Aabb::EMPTY;
+        }
+        // Arvo's method: each output extent is the sum of each matrix entry's smaller and
+        // larger product with the input extents.
+        let mut min = [self.m[0][3], self.m[1][3], self.m[2][3]];
+        let mut max = min;
+        for i in 0..3 {
+            for j in 0..3 {
+                let a = self.m[i][j] * bounds.min[j];
+                let b = self.m[i][j] * bounds.max[j];
+                min[i] += a.min(b);
+                max[i] += a.max(b);
+            }
+        }
+        Aabb::new(Vec3::from(min), Vec3::from(max))
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// A rotation as a unit quaternion, which unlike a matrix can be interpolated smoothly.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Quaternion {
+    pub v: Vec3,
+    pub w: f64,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Quaternion = Quaternion { v: Vec3 { x: 0.0, y: 0.0, z: 0.0 }, w: 1.0 };
+
+    /// Rotation by `angle` radians about `axis`, in the same sense as `Transform::rotate`.
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Quaternion { v: sin * axis.unit_vec(), w: cos }
+    }
+
+    fn dot(&self, other: &Quaternion) -> f64 {
+        self.v.dot(&other.v) + self.w * other.w
+    }
+
+    /// Rotation a fraction `t` of the way from this one to `other`, turning at a constant
+    /// rate the shorter way round.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Self {
+        // q and -q are the same rotation, so pick whichever is closer.
+        let (other, cos) = match self.dot(other) {
+            cos if cos < 0.0 => (Quaternion { v: -other.v, w: -other.w }, -cos),
+            cos => (*other, cos),
+        };
+        let (a, b) = if cos > 0.9995 {
+            // Nearly parallel, where lerping is accurate and the sines below aren't.
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        let q = Quaternion { v: a * self.v + b * other.v, w: a * self.w + b * other.w };
+        let length = q.dot(&q).sqrt();
+        Quaternion { v: q.v / length, w: q.w / length }
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        let Quaternion { v: Vec3 { x, y, z }, w } = *self;
+        let mut m = IDENTITY;
+        m[0][0] = 1.0 - 2.0 * (y * y + z * z);
+        m[0][1] = 2.0 * (x * y - w * z);
+        m[0][2] = 2.0 * (x * z + w * y);
+        m[1][0] = 2.0 * (x * y + w * z);
+        m[1][1] = 1.0 - 2.0 * (x * x + z * z);
+        m[1][2] = 2.0 * (y * z - w * x);
+        m[2][0] = 2.0 * (x * z - w * y);
+        m[2][1] = 2.0 * (y * z + w * x);
+        m[2][2] = 1.0 - 2.0 * (x * x + y * y);
+        // Rotations are orthogonal, so the inverse is the transpose.
+        let mut inv = IDENTITY;
+        for i in 0..3 {
+            for j in 0..3 {
+                inv[i][j] = m[j][i];
+            }
+        }
+        Transform { m, inv }
+    }
+}
+
+/// Where an object is at one moment of an `AnimatedTransform`: scaled, then rotated, then
+/// translated.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    /// Keyframe at `time` that leaves things where they are.
+    pub fn new(time: f64) -> Self {
+        Keyframe { time, translation: Vec3::default(), rotation: Quaternion::IDENTITY, scale: Vec3::new(1.0, 1.0, 1.0) }
+    }
+
+    pub fn translation(self, translation: Vec3) -> Self {
+        Keyframe { translation, ..self }
+    }
+
+    pub fn rotation(self, axis: Vec3, angle: f64) -> Self {
+        Keyframe { rotation: Quaternion::from_axis_angle(axis, angle), ..self }
+    }
+
+    pub fn scale(self, scale: Vec3) -> Self {
+        Keyframe { scale, ..self }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::scale(self.scale)
+            .then(&self.rotation.to_transform())
+            .then(&Transform::translate(self.translation))
+    }
+}
+
+/// A transform that changes over time, interpolated between keyframes. Translation and
+/// scale change linearly and rotation at a constant rate, and before the first keyframe or
+/// after the last it holds still.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    /// Animation through `keyframes`, in any order. Without any it's the identity.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        AnimatedTransform { keyframes }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return self.keyframes.first().map_or(Transform::IDENTITY, |k| k.transform());
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1].transform();
+        }
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = (time - a.time) / (b.time - a.time);
+        Keyframe {
+            time,
+            translation: (1.0 - t) * a.translation + t * b.translation,
+            rotation: a.rotation.slerp(&b.rotation, t),
+            scale: (1.0 - t) * a.scale + t * b.scale,
+        }.transform()
+    }
+
+    /// Box containing `bounds` transformed at every moment of the animation.
+    pub fn bounds(&self, bounds: &Aabb) -> Aabb {
+        if self.keyframes.is_empty() {
+            return *bounds;
+        }
+        let mut result = self.keyframes.iter().fold(Aabb::EMPTY, |b, k| b.union(&k.transform().bounds(bounds)));
+        if bounds.is_empty() {
+            return result;
+        }
+        for pair in self.keyframes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if a.rotation == b.rotation {
+                // Each corner then moves in a straight line, so the ends bound the whole way.
+                continue;
+            }
+            // Rotating, the corners sweep along arcs. They stay within the scaled distance of
+            // the farthest corner from the translation, which itself moves in a straight line.
+            let reach = (0..3).map(|i| bounds.min[i].abs().max(bounds.max[i].abs()) * a.scale[i].abs().max(b.scale[i].abs()));
+            let radius = reach.map(|r| r * r).sum::<f64>().sqrt();
+            let radius = Vec3::new(radius, radius, radius);
+            result = result
+                .union(&Aabb::new(a.translation - radius, a.translation + radius))
+                .union(&Aabb::new(b.translation - radius, b.translation + radius));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod transform_tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-12, "{:?} {:?}", a, b);
+    }
+
+    #[test]
+    fn test_basic() {
+        let p = Vec3::new(1.0, 2.0, 3.0);
+        assert_near(Transform::translate(Vec3::new(1.0, 0.0, -1.0)).point(&p), Vec3::new(2.0, 2.0, 2.0));
+        assert_near(Transform::translate(Vec3::new(1.0, 0.0, -1.0)).vector(&p), p);
+        assert_near(Transform::scale(Vec3::new(2.0, 1.0, 0.5)).point(&p), Vec3::new(2.0, 2.0, 1.5));
+        assert_near(Transform::rotate(Vec3::new(0.0, 0.0, 1.0), PI / 2.0).point(&p), Vec3::new(-2.0, 1.0, 3.0));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let t = Transform::scale(Vec3::new(1.0, 2.0, 3.0))
+            .then(&Transform::rotate(Vec3::new(1.0, 1.0, 0.0), 0.7))
+            .then(&Transform::translate(Vec3::new(-4.0, 5.0, 6.0)));
+        let p = Vec3::new(0.3, -1.2, 2.5);
+        assert_near(t.inverse().point(&t.point(&p)), p);
+        // Order matters: scaling happened first, so translation isn't scaled.
+        assert_near(t.point(&Vec3::default()), Vec3::new(-4.0, 5.0, 6.0));
+
+        let general = Transform::from_matrix(*t.matrix()).unwrap();
+        assert_near(general.inverse().point(&p), t.inverse().point(&p));
+        assert!(Transform::from_matrix([[1.0, 0.0, 0.0, 0.0], [2.0, 0.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]).is_none());
+    }
+
+    #[test]
+    fn test_normal() {
+        // Stays perpendicular to the plane x + y = 0 when squashed along x.
+        let t = Transform::scale(Vec3::new(0.25, 1.0, 1.0));
+        let (n, tangent) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.0));
+        assert!(t.normal(&n).dot(&t.vector(&tangent)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let b = Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let t = Transform::rotate(Vec3::new(0.0, 0.0, 1.0), PI / 4.0).then(&Transform::translate(Vec3::new(5.0, 0.0, 0.0)));
+        let rotated = t.bounds(&b);
+        let half = 2.0f64.sqrt();
+        assert_near(rotated.min, Vec3::new(5.0 - half, -half, -1.0));
+        assert_near(rotated.max, Vec3::new(5.0 + half, half, 1.0));
+        assert!(t.bounds(&Aabb::EMPTY).is_empty());
+    }
+
+    #[test]
+    fn test_slerp() {
+        let axis = Vec3::new(1.0, 2.0, -1.0);
+        let (a, b) = (Quaternion::from_axis_angle(axis, 0.3), Quaternion::from_axis_angle(axis, 1.9));
+        let p = Vec3::new(0.5, -1.0, 2.0);
+        for &t in &[0.0, 0.25, 0.5, 1.0] {
+            let expected = Transform::rotate(axis, 0.3 + 1.6 * t);
+            assert_near(a.slerp(&b, t).to_transform().point(&p), expected.point(&p));
+        }
+        // Goes the short way round, from just under a half turn to just over.
+        let (a, b) = (Quaternion::from_axis_angle(axis, PI - 0.1), Quaternion::from_axis_angle(axis, PI + 0.1));
+        assert_near(a.slerp(&b, 0.5).to_transform().point(&p), Transform::rotate(axis, PI).point(&p));
+    }
+
+    #[test]
+    fn test_animated() {
+        let animation = AnimatedTransform::new(vec![
+            Keyframe::new(1.0).translation(Vec3::new(4.0, 0.0, 0.0)).rotation(Vec3::new(0.0, 0.0, 1.0), PI / 2.0),
+            Keyframe::new(0.0).scale(Vec3::new(2.0, 2.0, 2.0)),
+        ]);
+        let p = Vec3::new(1.0, 0.0, 0.0);
+        assert_near(animation.at(-1.0).point(&p), Vec3::new(2.0, 0.0, 0.0));
+        assert_near(animation.at(2.0).point(&p), Vec3::new(4.0, 1.0, 0.0));
+        let half = 1.5 / 2.0f64.sqrt();
+        assert_near(animation.at(0.5).point(&p), Vec3::new(2.0 + half, half, 0.0));
+
+        // Every point along the way stays inside the bounds.
+        let b = Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let bounds = animation.bounds(&b);
+        for i in 0..=100 {
+            let t = animation.at(i as f64 / 100.0);
+            for &corner in &[b.min, b.max, Vec3::new(b.min.x, b.max.y, b.min.z), Vec3::new(b.max.x, b.min.y, b.max.z)] {
+                let q = t.point(&corner);
+                assert!((0..3).all(|k| bounds.min[k] <= q[k] + 1e-12 && q[k] <= bounds.max[k] + 1e-12), "{:?}", q);
+            }
+        }
+        assert_eq!(AnimatedTransform::default().at(3.0), Transform::IDENTITY);
+        // A keyframe at a NaN time sorts after the rest rather than panicking.
+        let animation = AnimatedTransform::new(vec![Keyframe::new(f64::NAN), Keyframe::new(0.0)]);
+        assert_eq!(animation.keyframes()[0].time, 0.0);
+    }
+}
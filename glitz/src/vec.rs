@@ -1,4 +1,4 @@
-use std::ops::{Sub, Add, Div, Mul, Neg};
+use std::ops::{Sub, Add, Div, Mul, Neg, Index};
 use rand::distributions::Standard;
 use rand::distributions::Uniform;
 use rand::prelude::Distribution;
@@ -49,7 +49,7 @@ impl Vec3 {
 
     #[inline]
     pub fn refract(&self, n: &Vec3, etai_over_etat: f64) -> Vec3 {
-        let cos_theta = ((-*self).dot(&n)).min(1.0);
+        let cos_theta = ((-*self).dot(n)).min(1.0);
         let r_out_perp = etai_over_etat * (*self + cos_theta * *n);
         let r_out_parallel = -((1.0 - r_out_perp.dot(&r_out_perp)).abs().sqrt()) * *n;
         r_out_perp + r_out_parallel
@@ -62,6 +62,16 @@ impl Vec3 {
         (self.x.abs() < e) && (self.y.abs() < e) && (self.z.abs() < e)
     }
 
+    #[inline]
+    pub fn min(&self, other: &Self) -> Self {
+        Self::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    #[inline]
+    pub fn max(&self, other: &Self) -> Self {
+        Self::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
     #[inline]
     pub fn random_unit_vec(rng: &mut impl Rng) -> Self {
         Vec3::new(rng.sample(StandardNormal), rng.sample(StandardNormal), rng.sample(StandardNormal)).unit_vec()
@@ -162,6 +172,20 @@ impl Div<f64> for Vec3 {
     }
 }
 
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    #[inline]
+    fn index(&self, axis: usize) -> &Self::Output {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 index out of bounds: {}", axis),
+        }
+    }
+}
+
 impl From<[f64; 3]> for Vec3 {
     fn from(arr: [f64; 3]) -> Self {
         Vec3 {
@@ -216,6 +240,22 @@ mod vec3_tests {
         assert_eq!(5.0 * vec, Vec3::new(0.0, 5.0, 10.0));
     }
 
+    #[test]
+    fn test_min_max() {
+        let vec1 = Vec3::new(0.0, 1.0, 2.0);
+        let vec2 = Vec3::new(2.0, 1.0, 0.0);
+        assert_eq!(vec1.min(&vec2), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(vec1.max(&vec2), Vec3::new(2.0, 1.0, 2.0));
+    }
+
+    #[test]
+    fn test_index() {
+        let vec = Vec3::new(0.0, 1.0, 2.0);
+        assert_eq!(vec[0], 0.0);
+        assert_eq!(vec[1], 1.0);
+        assert_eq!(vec[2], 2.0);
+    }
+
     #[test]
     fn test_length() {
         let vec1 = Vec3::new(0.0, 1.0, 2.0);
@@ -17,6 +17,11 @@ impl Color {
         Color { r, g, b }
     }
 
+    /// Perceived brightness of a linear sRGB color.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn write_16(self, buffer: &mut [u8]) {
         self.write_16_sampled(buffer, 1);
     }
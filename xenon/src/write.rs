@@ -6,6 +6,16 @@ use crate::color::Color;
 use rayon::prelude::*;
 
 pub fn fn_to_png(width: u32, height: u32, file: File, func: impl Fn(u32, u32) -> Color + Sync + Send) {
+    fn_to_png_with(width, height, file, func, write_pixel);
+}
+
+/// Like `fn_to_png`, but stores values as they are rather than gamma encoded, for images
+/// holding data such as normals or depth.
+pub fn fn_to_png_linear(width: u32, height: u32, file: File, func: impl Fn(u32, u32) -> Color + Sync + Send) {
+    fn_to_png_with(width, height, file, func, write_pixel_linear);
+}
+
+fn fn_to_png_with(width: u32, height: u32, file: File, func: impl Fn(u32, u32) -> Color + Sync + Send, write: fn(&mut [u8], Color)) {
     let mut data = vec![0; (width * height * 6) as usize];
     #[cfg(feature = "rayon")]
     let iter = data.par_chunks_exact_mut(6);
@@ -14,7 +24,7 @@ pub fn fn_to_png(width: u32, height: u32, file: File, func: impl Fn(u32, u32) ->
     iter.enumerate().for_each(|(index, pixel)| {
         let i = (index as u32) % width;
         let j = height - ((index as u32) / width);
-        write_pixel(pixel, func(i, j));
+        write(pixel, func(i, j));
     });
     write_to_file(data, file, width, height);
 }
@@ -23,13 +33,24 @@ pub fn write_pixel(buffer: &mut [u8], pixel: Color) {
     let red = (pixel.r.sqrt().clamp(0.0, 0.999) * 65535.0) as u16;
     let green = (pixel.g.sqrt().clamp(0.0, 0.999) * 65535.0) as u16;
     let blue = (pixel.b.sqrt().clamp(0.0, 0.999) * 65535.0) as u16;
-    let mut to_write = [
+    let to_write = [
         (red >> 8) as u8, red as u8, 
         (green >> 8) as u8, green as u8, 
         (blue >> 8) as u8, blue as u8, 
     ];
 
-    buffer.copy_from_slice(&mut to_write);
+    buffer.copy_from_slice(&to_write);
+}
+
+pub fn write_pixel_linear(buffer: &mut [u8], pixel: Color) {
+    let red = (pixel.r.clamp(0.0, 1.0) * 65535.0) as u16;
+    let green = (pixel.g.clamp(0.0, 1.0) * 65535.0) as u16;
+    let blue = (pixel.b.clamp(0.0, 1.0) * 65535.0) as u16;
+    buffer.copy_from_slice(&[
+        (red >> 8) as u8, red as u8,
+        (green >> 8) as u8, green as u8,
+        (blue >> 8) as u8, blue as u8,
+    ]);
 }
 
 fn write_to_file(data: Vec<u8>, file: File, width: u32, height: u32) {